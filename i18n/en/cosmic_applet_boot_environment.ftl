@@ -1,4 +1,162 @@
 active-boot-env = Active Boot Environment
 boot-settings = Boot settings...
 no-active-boot-env = No active boot environment detected
-reboot-into = Reboot into...
+reboot-into-once = Reboot once into...
+make-default = Make default...
+reboot-required = Reboot to start using { $name }
+reboot-now = Reboot now
+reboot-title = Reboot into { $name }?
+reboot-countdown = { $seconds ->
+    [one] Rebooting in one second.
+   *[other] Rebooting in { $seconds } seconds.
+}
+reboot-inhibited = { $count ->
+    [one] This program may prevent the reboot:
+   *[other] These { $count } programs may prevent the reboot:
+}
+inhibitor = { $who }: { $why }
+trial-title = Trying out { $name }
+trial-details = This boot environment was started for one boot only. { $default } will start next time unless you keep this one.
+trial-keep = Keep using this environment
+trial-go-back = Go back next boot
+settings = Settings
+back = Back
+notify-boot-changes = Notify me when the boot environment to start next is changed
+notify-environment-changes = Notify me when boot environments are created or deleted
+notify-summary = Boot environments changed
+notify-default = { $name } will start from now on.
+notify-boot-once = { $name } will start next time only.
+notify-boot-once-cleared = The default will start next time.
+notify-added = { $name } was created.
+notify-removed = { $name } was deleted.
+undo = Undo
+loading-snapshots = Loading snapshots...
+no-snapshots = No snapshots
+snapshot-details = { $space }, created { $created }
+snapshot-name = Snapshot name
+snapshot-description = Description (optional)
+snapshot-now = Snapshot now
+snapshot-in-progress = Taking a snapshot of { $name }...
+snapshot-created = Created snapshot { $name }
+snapshot-before-update = Before updating packages
+snapshot-before-system-upgrade = Before upgrading the system
+snapshot-before-packages = { $count ->
+    [one] Before changing { $packages }
+   *[other] Before changing { $count } packages: { $packages }
+}
+cancel = Cancel
+rollback = Roll back
+rollback-title = Roll back { $name } to { $snapshot }?
+rollback-no-loss = No later snapshots will be lost.
+rollback-destroys = { $count ->
+    [one] The following later snapshot will be destroyed:
+   *[other] The following { $count } later snapshots will be destroyed:
+}
+rollback-may-destroy = { $count ->
+    [one] The following snapshot was taken in the same second, and will be destroyed if it was taken afterwards:
+   *[other] The following { $count } snapshots were taken in the same second, and will be destroyed if they were taken afterwards:
+}
+rollback-active-warning = This is the boot environment you are currently running. Rolling it back will discard changes out from under running programs.
+rollback-active-override = Roll back the running environment anyway
+delete = Delete
+delete-selected = Delete { $count } selected
+destroy-snapshots-title = { $count ->
+    [one] Delete this snapshot?
+   *[other] Delete these { $count } snapshots?
+}
+destroy-snapshots-space = At least { $space } will be freed.
+destroy-snapshot-failed = Could not delete { $name }: { $reason }
+save = Save
+rename = Rename
+rename-placeholder = New name
+name-empty = The name cannot be empty.
+name-too-long = The name cannot be longer than { $max } characters.
+name-reserved = The name cannot be "." or "..".
+name-invalid-character = The name cannot contain "{ $character }". Use letters, digits, "_", "-", ":" and "." only.
+name-exists = Another boot environment already has this name.
+clear = Clear
+describe = Edit description
+describe-placeholder = Description
+describe-preview = Shown as "{ $label }" ({ $count }/{ $max })
+description-too-long = The description cannot be longer than { $max } characters.
+destroy-environment = Delete environment
+destroy-environment-blocked = The running boot environment and the one scheduled to boot next cannot be deleted.
+environment-name-unknown = This boot environment cannot be renamed, deleted or mounted until the service reports its name.
+destroy-environment-unknown = This boot environment cannot be deleted until the service reports whether it is running or scheduled to boot.
+destroy-environment-title = Delete { $name }?
+destroy-environment-prompt = This cannot be undone. Type "{ $name }" to confirm.
+destroy-environment-placeholder = Boot environment name
+destroy-force-unmount = Unmount it first if it is mounted
+destroy-with-snapshots = Also delete its snapshots
+mount = Mount
+mount-read-only = Read-only
+mounted-at = Mounted at { $mountpoint }
+open = Open
+unmount = Unmount
+force-unmount = Force unmount
+other-property = { $name }: { $value }
+status-running = Running
+status-default = Default
+status-boot-once = Next boot only
+total-space = { $count ->
+    [one] One boot environment using { $space }
+   *[other] { $count } boot environments using { $space }
+}
+size-bytes = { $value } B
+size-kib = { $value } KiB
+size-mib = { $value } MiB
+size-gib = { $value } GiB
+size-tib = { $value } TiB
+size-pib = { $value } PiB
+connecting = Connecting to the system bus
+connecting-details = This should only take a moment...
+disconnected = Not connected to the system bus
+disconnected-details = { $reason } Retrying automatically...
+reconnect = Reconnect
+service-unavailable = Boot environment service unavailable
+service-unavailable-details = Waiting for the beadm service to start...
+retry = Retry
+pending = Waiting for the boot environment service...
+pending-activate = Make { $name } the default
+pending-activate-once = Reboot once into { $name }
+pending-snapshot = Snapshot { $name }
+pending-rollback = Roll back { $name } to { $snapshot }
+pending-destroy-snapshots = { $count ->
+    [one] Delete one snapshot
+   *[other] Delete { $count } snapshots
+}
+pending-destroy-environment = Delete { $name }
+pending-rename = Rename { $name } to { $new_name }
+pending-describe = Edit the description of { $name }
+pending-mount = Mount { $name }
+pending-unmount = Unmount { $name }
+inhibit-who = Boot Environment Applet
+inhibit-rollback = Rolling back { $name } to { $snapshot }
+inhibit-destroy-snapshots = { $count ->
+    [one] Deleting a snapshot of { $name }
+   *[other] Deleting { $count } snapshots of { $name }
+}
+inhibit-destroy-environment = Deleting { $name }
+inhibit-rename = Renaming { $name } to { $new_name }
+error-load = Could not load boot environments
+error-load-snapshots = Could not load snapshots
+error-activate = Could not activate { $name }
+error-create-snapshot = Could not create a snapshot
+error-rollback = Could not roll back to { $snapshot }
+error-destroy-environment = Could not delete { $name }
+error-mount = Could not mount { $name }
+error-unmount = Could not unmount { $name }
+error-open = Could not open { $mountpoint }
+error-no-runtime-dir = XDG_RUNTIME_DIR is not set, so there is nowhere private to mount it.
+error-pending = Gave up on "{ $action }"
+error-refused = Did not "{ $action }"
+error-rollback-now-active = The boot environment is now running.
+error-destroy-now-unsafe = The boot environment is now running or scheduled to boot.
+error-reboot = Could not reboot
+error-access-denied = You are not authorized to do this.
+error-no-such-object = The boot environment no longer exists.
+error-service-unknown = The boot environment service is not running.
+error-timeout = The boot environment service did not respond in time.
+error-disconnected = The system bus is unavailable: { $details }
+error-backend = { $details }
+error-other = { $details }
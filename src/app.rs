@@ -4,90 +4,414 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::collections::HashMap;
+//! The COSMIC applet: its state, messages and views.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 
 use cosmic::applet::{menu_button, padded_control};
+use cosmic::cosmic_config::{self, CosmicConfigEntry};
 use cosmic::cosmic_theme::Spacing;
 use cosmic::iced::widget::{column, row};
 use cosmic::iced::{window::Id, Alignment, Length, Subscription};
 use cosmic::iced_winit::commands::popup::{destroy_popup, get_popup};
 use cosmic::prelude::*;
 use cosmic::theme;
-use cosmic::widget::{divider, dropdown, text};
-use futures_util::{SinkExt, StreamExt};
-use zbus::fdo::ObjectManagerProxy;
+use cosmic::widget::{button, checkbox, divider, dropdown, text, text_input};
+use cosmic::Application;
+use futures_util::{FutureExt, StreamExt};
+use icu_decimal::input::Decimal;
 use zbus::zvariant;
 
-use crate::dbus::BootEnvironmentProxy;
+use crate::backend::{Backend, DBusBackend, Event, MemoryBackend, ShutdownLock, BACKEND_ENV};
+use crate::config::{Config, State};
+use crate::error::AppError;
 use crate::fl;
+use crate::i18n;
+use crate::login1::Inhibitor;
+use crate::model::{environment_label, BootEnvironmentChanges, BootEnvironmentObject, Snapshot};
+use crate::notifier::{DBusNotifier, MemoryNotifier, Notification, Notifier};
+
+/// The longest boot environment name accepted by the applet, in bytes.
+///
+/// ZFS limits the full dataset name (including the pool and parent datasets) to
+/// 255 bytes, so the service may still reject shorter names.
+const MAX_NAME_LEN: usize = 255;
+
+/// The longest boot environment description accepted by the applet, in
+/// characters. Anything longer is unwieldy in the panel.
+const MAX_DESCRIPTION_LEN: usize = 128;
+
+/// Reasons a proposed boot environment name is unacceptable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NameError {
+    /// The name is empty.
+    Empty,
+    /// The name is longer than [`MAX_NAME_LEN`].
+    TooLong,
+    /// The name is `.` or `..`.
+    Reserved,
+    /// The name contains a character ZFS does not allow in dataset names.
+    InvalidCharacter(char),
+    /// Another boot environment already has this name.
+    Exists,
+}
+
+impl NameError {
+    /// A localized explanation of this error.
+    fn message(&self) -> String {
+        match self {
+            NameError::Empty => fl!("name-empty"),
+            NameError::TooLong => fl!("name-too-long", max = MAX_NAME_LEN),
+            NameError::Reserved => fl!("name-reserved"),
+            NameError::InvalidCharacter(c) => {
+                fl!("name-invalid-character", character = c.to_string())
+            }
+            NameError::Exists => fl!("name-exists"),
+        }
+    }
+}
+
+/// Check a proposed new name for the boot environment at `path` against the
+/// ZFS dataset naming rules and the names of the other boot environments.
+fn validate_name(
+    name: &str,
+    path: &zvariant::OwnedObjectPath,
+    environments: &[BootEnvironmentObject],
+) -> Result<(), NameError> {
+    if name.is_empty() {
+        return Err(NameError::Empty);
+    }
+    if name.len() > MAX_NAME_LEN {
+        return Err(NameError::TooLong);
+    }
+    if name == "." || name == ".." {
+        return Err(NameError::Reserved);
+    }
+    // ZFS also permits spaces, but they are more trouble than they are worth
+    // on a kernel command line.
+    if let Some(c) = name
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':' | '.')))
+    {
+        return Err(NameError::InvalidCharacter(c));
+    }
+    if environments
+        .iter()
+        .any(|env| env.name == name && &env.path != path)
+    {
+        return Err(NameError::Exists);
+    }
+    Ok(())
+}
+
+/// The property of a boot environment being edited inline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EditorField {
+    /// The name of the boot environment.
+    Name,
+    /// The description of the boot environment.
+    Description,
+}
 
-/// Represents a boot environment object exposed on the bus.
+/// An inline editor for a property of a boot environment.
 #[derive(Debug, Clone)]
-pub struct BootEnvironmentObject {
-    /// The D-Bus object path foight n boot environment.
+struct Editor {
+    /// The D-Bus object path of the boot environment being edited.
     pub path: zvariant::OwnedObjectPath,
-    /// The name of this boot environment.
-    pub name: String,
-    /// A description for this boot environment, if any.
-    pub description: Option<String>,
-    /// Whether the system is currently booted into this boot environment.
-    pub active: bool,
-    /// Whether the system will reboot into this environment.
-    pub next_boot: bool,
-    /// Whether the system will reboot into this environment temporarily.
-    pub boot_once: bool,
-    /// Unix timestamp for when this boot environment was created.
-    pub created: i64,
-}
-
-impl BootEnvironmentObject {
-    /// Construct a `BootEnvironmentObject` from a D-Bus dictionary of properties.
-    pub fn from_properties<'a, K, V>(
+    /// The property being edited.
+    pub field: EditorField,
+    /// The current contents of the editor.
+    pub value: String,
+    /// Whether the change is being applied.
+    pub in_progress: bool,
+    /// Why the last attempt to apply the change failed, if it did.
+    pub error: Option<String>,
+}
+
+/// The names of the snapshots that rolling back to `snapshot` would destroy,
+/// that is, every snapshot taken after it.
+///
+/// Returns the snapshots taken in a later second, then those taken in the same
+/// second as `snapshot`. Timestamps only have a resolution of a second and the
+/// service lists snapshots in no particular order, so there's no telling
+/// whether the latter were taken before or after it.
+fn later_snapshots(snapshots: &[Snapshot], snapshot: &Snapshot) -> (Vec<String>, Vec<String>) {
+    let later = snapshots
+        .iter()
+        .filter(|s| s.created > snapshot.created)
+        .map(|s| s.name.clone())
+        .collect();
+    let same_second = snapshots
+        .iter()
+        .filter(|s| s.created == snapshot.created && s.name != snapshot.name)
+        .map(|s| s.name.clone())
+        .collect();
+    (later, same_second)
+}
+
+/// A destructive action that is waiting for the user to confirm it.
+#[derive(Debug, Clone)]
+enum Confirmation {
+    /// Roll a boot environment back to one of its snapshots.
+    Rollback {
+        /// The D-Bus object path of the boot environment.
         path: zvariant::OwnedObjectPath,
-        props: &'a HashMap<K, V>,
-    ) -> Result<Self, zbus::Error>
-    where
-        K: std::borrow::Borrow<str> + Eq + std::hash::Hash,
-        V: std::borrow::Borrow<zvariant::Value<'a>>,
-    {
-        // This is a gross but useful wrapper around downcast_ref().
-        fn get_prop<'a, T, K, V>(
-            props: &'a HashMap<K, V>,
-            name: &str,
-        ) -> Result<T, zbus::zvariant::Error>
-        where
-            K: std::borrow::Borrow<str> + Eq + std::hash::Hash,
-            V: std::borrow::Borrow<zvariant::Value<'a>>,
-            T: TryFrom<&'a zvariant::Value<'a>>,
-            <T as TryFrom<&'a zvariant::Value<'a>>>::Error: Into<zvariant::Error>,
-        {
-            props
-                .get(name)
-                .ok_or(zbus::zvariant::Error::IncorrectType)?
-                .borrow()
-                .downcast_ref()
-        }
-
-        // Special handling for optional properties.
-        let description_str: String = get_prop(props, "Description")?;
-        let description = if description_str.is_empty() {
-            None
-        } else {
-            Some(description_str)
+        /// The snapshot to roll back to.
+        snapshot: String,
+        /// Names of the later snapshots that the rollback will destroy.
+        destroyed: Vec<String>,
+        /// Names of the snapshots taken in the same second as `snapshot`,
+        /// which the rollback destroys if they were taken after it.
+        maybe_destroyed: Vec<String>,
+        /// Whether the boot environment is the one currently booted.
+        active: bool,
+        /// Whether the user has explicitly chosen to roll back the booted
+        /// environment anyway.
+        override_active: bool,
+    },
+    /// Destroy one or more snapshots of a boot environment.
+    DestroySnapshots {
+        /// The D-Bus object path of the boot environment.
+        path: zvariant::OwnedObjectPath,
+        /// The snapshots to destroy.
+        snapshots: Vec<String>,
+        /// The combined space used by these snapshots, in bytes.
+        space: u64,
+    },
+    /// Destroy a boot environment.
+    DestroyEnvironment {
+        /// The D-Bus object path of the boot environment.
+        path: zvariant::OwnedObjectPath,
+        /// The name of the boot environment, which the user must type.
+        name: String,
+        /// What the user has typed so far.
+        typed: String,
+        /// Whether to forcibly unmount the boot environment if it is mounted.
+        force_unmount: bool,
+        /// Whether to destroy the snapshots of the boot environment as well.
+        snapshots: bool,
+    },
+}
+
+impl Confirmation {
+    /// Whether the user has supplied everything required to proceed.
+    fn can_confirm(&self) -> bool {
+        match self {
+            Confirmation::Rollback {
+                active,
+                override_active,
+                ..
+            } => !active || *override_active,
+            Confirmation::DestroySnapshots { .. } => true,
+            Confirmation::DestroyEnvironment { name, typed, .. } => typed.trim() == name,
+        }
+    }
+
+    /// Why this can no longer be carried out, if its boot environment has
+    /// gone or changed since the user was asked.
+    fn refusal(&self, environments: &[BootEnvironmentObject]) -> Option<AppError> {
+        let path = match self {
+            Confirmation::Rollback { path, .. }
+            | Confirmation::DestroySnapshots { path, .. }
+            | Confirmation::DestroyEnvironment { path, .. } => path,
         };
+        let Some(env) = environments.iter().find(|env| &env.path == path) else {
+            return Some(AppError::NoSuchObject);
+        };
+        match self {
+            // The user was warned about rolling back the running environment
+            // only if it was running when they were asked.
+            Confirmation::Rollback { active: false, .. } if env.may_be_active() => {
+                Some(AppError::Other(fl!("error-rollback-now-active")))
+            }
+            Confirmation::DestroyEnvironment { .. } if !env.can_destroy() => {
+                Some(AppError::Other(fl!("error-destroy-now-unsafe")))
+            }
+            _ => None,
+        }
+    }
+}
 
-        Ok(BootEnvironmentObject {
-            path,
-            name: get_prop(props, "Name")?,
-            description,
-            active: get_prop(props, "Active")?,
-            next_boot: get_prop(props, "NextBoot")?,
-            boot_once: get_prop(props, "BootOnce")?,
-            created: get_prop(props, "Created")?,
-        })
+/// The most error banners shown at once; older ones are dropped.
+const MAX_BANNERS: usize = 3;
+
+/// A dismissable error shown at the top of the popup.
+#[derive(Debug, Clone)]
+struct Banner {
+    /// Identifies this banner for dismissal and retries.
+    id: u64,
+    /// What the applet was trying to do.
+    summary: String,
+    /// Why it failed.
+    error: AppError,
+    /// A message that retries the failed operation, if that makes sense.
+    retry: Option<Message>,
+}
+
+/// The delay before the first attempt to reconnect, doubled after each
+/// failure.
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// The longest delay between attempts to reconnect.
+const MAX_RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// The state of the connection to the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ConnectionState {
+    /// Trying to connect.
+    Connecting {
+        /// How many attempts in a row have failed before this one.
+        failures: u32,
+    },
+    /// Connected, though the service itself may not be running.
+    Connected,
+    /// Waiting to try again after failing to connect or losing the
+    /// connection.
+    Waiting {
+        /// How many attempts in a row have failed.
+        failures: u32,
+        /// Why the last attempt failed or the connection was lost.
+        error: AppError,
+    },
+}
+
+/// How long an action waits for the service to become available before it is
+/// given up on.
+const PENDING_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// An action requested while the service was unreachable, to be performed
+/// once it is back.
+#[derive(Debug, Clone)]
+struct PendingAction {
+    /// Identifies this action for cancellation and expiry.
+    id: u64,
+    /// What the action will do, as shown in the popup.
+    label: String,
+    /// The message that performs the action.
+    message: Message,
+    /// The confirmation the action was submitted from, restored before it
+    /// is performed.
+    confirmation: Option<Confirmation>,
+    /// The editor the action was submitted from, restored before it is
+    /// performed.
+    editor: Option<Editor>,
+}
+
+/// How many seconds to count down before rebooting, giving the user a chance
+/// to change their mind or close what they are doing.
+const REBOOT_COUNTDOWN: u32 = 10;
+
+/// A reboot about to happen unless it is cancelled.
+#[derive(Debug, Clone)]
+struct RebootCountdown {
+    /// Identifies this countdown, so that ticks of a cancelled one are
+    /// ignored.
+    id: u64,
+    /// The boot environment that will be booted into.
+    label: String,
+    /// Seconds left before rebooting.
+    remaining: u32,
+    /// Programs that may stop the reboot, refreshed every second.
+    inhibitors: Vec<Inhibitor>,
+}
+
+/// How long to collect changes before notifying the user about them, so that
+/// bursts of signals cause at most one notification.
+const NOTIFY_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The most notifications whose buttons are remembered.
+const MAX_NOTIFICATIONS: usize = 8;
+
+/// What the user was last told about: which boot environments exist and
+/// which will start next.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct BootTargets {
+    /// The default boot environment, if any.
+    next_boot: Option<zvariant::OwnedObjectPath>,
+    /// The boot environment to start next time only, if any.
+    boot_once: Option<zvariant::OwnedObjectPath>,
+    /// The label of every boot environment.
+    labels: HashMap<zvariant::OwnedObjectPath, String>,
+}
+
+impl BootTargets {
+    fn new(environments: &[BootEnvironmentObject]) -> Self {
+        BootTargets {
+            next_boot: environments
+                .iter()
+                .find(|env| env.next_boot)
+                .map(|env| env.path.clone()),
+            boot_once: environments
+                .iter()
+                .find(|env| env.boot_once)
+                .map(|env| env.path.clone()),
+            labels: environments
+                .iter()
+                .map(|env| (env.path.clone(), env.label()))
+                .collect(),
+        }
+    }
+
+    /// The label of a boot environment, falling back to its object path.
+    fn label(&self, path: &zvariant::OwnedObjectPath) -> String {
+        self.labels
+            .get(path)
+            .cloned()
+            .unwrap_or_else(|| path.to_string())
+    }
+
+    /// Take in a change that the applet asked for, to `path` and for the next
+    /// boot only if `temporary`, if `current` shows it has happened. Returns
+    /// whether it has.
+    fn take_in_request(
+        &mut self,
+        path: &zvariant::OwnedObjectPath,
+        temporary: bool,
+        current: &BootTargets,
+    ) -> bool {
+        if temporary {
+            if current.boot_once.as_ref() != Some(path) {
+                return false;
+            }
+            self.boot_once = current.boot_once.clone();
+        } else {
+            if current.next_boot.as_ref() != Some(path) {
+                return false;
+            }
+            self.next_boot = current.next_boot.clone();
+            // Making an environment the default also cancels booting once.
+            if current.boot_once.is_none() {
+                self.boot_once = None;
+            }
+        }
+        true
+    }
+
+    /// The labels of boot environments that aren't in `other`, sorted.
+    fn labels_missing_from(&self, other: &BootTargets) -> Vec<String> {
+        let mut labels: Vec<String> = self
+            .labels
+            .iter()
+            .filter(|(path, _)| !other.labels.contains_key(*path))
+            .map(|(_, label)| label.clone())
+            .collect();
+        labels.sort();
+        labels
     }
 }
 
+/// A notification that has been shown.
+#[derive(Debug, Clone)]
+struct SentNotification {
+    id: u32,
+    /// Whether it describes changes, and should be replaced by the next
+    /// notification that does.
+    changes: bool,
+    /// The message sent by each of its buttons, by action key.
+    actions: Vec<(String, Message)>,
+}
+
 /// The application model stores app-specific state used to describe its interface and
 /// drive its logic.
 pub struct AppModel {
@@ -97,8 +421,90 @@ pub struct AppModel {
     popup: Option<Id>,
     /// List of boot environments.
     environments: Vec<BootEnvironmentObject>,
-    /// The active D-Bus connection, if any.
-    conn: Option<zbus::Connection>,
+    /// Snapshots for each boot environment, keyed by object path.
+    snapshots: HashMap<zvariant::OwnedObjectPath, Vec<Snapshot>>,
+    /// The boot environment whose snapshots are currently shown, if any.
+    expanded: Option<zvariant::OwnedObjectPath>,
+    /// Names of the selected snapshots of the expanded boot environment.
+    selected_snapshots: HashSet<String>,
+    /// Snapshots of the expanded boot environment that could not be
+    /// destroyed, along with the reason.
+    snapshot_failures: Vec<(String, String)>,
+    /// Name for the next snapshot of the active boot environment.
+    snapshot_name: String,
+    /// Description for the next snapshot of the active boot environment.
+    snapshot_description: String,
+    /// Whether a snapshot is currently being created.
+    snapshot_in_progress: bool,
+    /// The name of the most recently created snapshot, if any.
+    last_snapshot: Option<String>,
+    /// A destructive action awaiting confirmation, if any.
+    confirmation: Option<Confirmation>,
+    /// The open inline editor, if any.
+    editor: Option<Editor>,
+    /// Whether boot environments should be mounted read-only.
+    mount_read_only: bool,
+    /// The boot environment being mounted or unmounted, if any.
+    mount_in_progress: Option<zvariant::OwnedObjectPath>,
+    /// The boot environment that last failed to unmount, if any.
+    unmount_failed: Option<zvariant::OwnedObjectPath>,
+    /// Whether the boot environment service is believed to be on the bus.
+    service_available: bool,
+    /// Incremented each time the service gains a new owner, so that signal
+    /// subscriptions are restarted against it.
+    service_generation: u64,
+    /// Whether a full reload of the boot environments is already scheduled.
+    reload_pending: bool,
+    /// Errors to show the user.
+    banners: Vec<Banner>,
+    /// The id of the next banner.
+    next_banner_id: u64,
+    /// The state of the connection to the backend.
+    connection: ConnectionState,
+    /// Actions waiting for the service, oldest first.
+    pending: Vec<PendingAction>,
+    /// The ID to give the next pending action.
+    next_pending_id: u64,
+    /// Whether logind will let the user reboot.
+    can_reboot: bool,
+    /// The reboot being counted down to, if any.
+    reboot: Option<RebootCountdown>,
+    /// The ID to give the next reboot countdown.
+    next_reboot_id: u64,
+    /// Whether the boot environments have been loaded since the applet
+    /// started.
+    first_load_done: bool,
+    /// The boot environment that the system was booted into temporarily,
+    /// until the user decides whether to keep it.
+    trial: Option<zvariant::OwnedObjectPath>,
+    /// Whether the user has been notified about the boot environment being
+    /// tried out.
+    trial_notified: bool,
+    /// The applet's settings.
+    config: Config,
+    /// Where the settings are stored, if they can be.
+    config_handler: Option<cosmic_config::Config>,
+    /// What the applet remembers between sessions.
+    state: State,
+    /// Where that is stored, if it can be.
+    state_handler: Option<cosmic_config::Config>,
+    /// Whether the settings are shown in place of everything else.
+    settings_open: bool,
+    /// Where desktop notifications are sent, once connected.
+    notifier: Option<Arc<dyn Notifier>>,
+    /// What the user was last told about, once the boot environments have
+    /// been loaded.
+    notified: Option<BootTargets>,
+    /// Whether a notification about recent changes is already scheduled.
+    notify_pending: bool,
+    /// The boot environment that the applet last asked to boot, and whether
+    /// only once, until the change shows up. Changes asked for through the
+    /// applet aren't news, whether from the popup or a notification.
+    requested_target: Option<(zvariant::OwnedObjectPath, bool)>,
+    /// Recently shown notifications, oldest first.
+    notifications: Vec<SentNotification>,
+    /// Where boot environments come from, once connected.
+    backend: Option<Arc<dyn Backend>>,
 }
 
 /// Messages emitted by the application and its widgets.
@@ -107,53 +513,320 @@ pub enum Message {
     TogglePopup,
     PopupClosed(Id),
     BootSettingsClicked,
-    ActivateEnvironment(zvariant::OwnedObjectPath),
+    Connect,
+    RetryConnect(u32),
+    ConnectFailed(AppError),
+    ConnectionLost(AppError),
+    ShowError(String, AppError, Option<Box<Message>>),
+    DismissBanner(u64),
+    RetryBanner(u64),
+    ActivateEnvironment(zvariant::OwnedObjectPath, bool),
+    Activated(zvariant::OwnedObjectPath, bool, Result<(), AppError>),
+    ToggleExpanded(zvariant::OwnedObjectPath),
     BootEnvironmentsLoaded(Vec<BootEnvironmentObject>),
-    Connected(zbus::Connection),
+    SnapshotsLoaded(zvariant::OwnedObjectPath, Vec<Snapshot>),
+    SnapshotNameInput(String),
+    SnapshotDescriptionInput(String),
+    CreateSnapshot,
+    SnapshotCreated(zvariant::OwnedObjectPath, Result<String, AppError>),
+    RequestRollback(zvariant::OwnedObjectPath, String),
+    OverrideActiveRollback(bool),
+    ConfirmAction,
+    CancelAction,
+    RolledBack(zvariant::OwnedObjectPath, String, Result<(), AppError>),
+    SelectSnapshot(String, bool),
+    RequestDestroySnapshots(Vec<String>),
+    StartRename(zvariant::OwnedObjectPath),
+    EditorInput(String),
+    SubmitEditor,
+    CancelEditor,
+    Renamed(zvariant::OwnedObjectPath, String, Result<(), AppError>),
+    StartDescribe(zvariant::OwnedObjectPath),
+    Described(zvariant::OwnedObjectPath, Result<(), AppError>),
+    RequestDestroyEnvironment(zvariant::OwnedObjectPath),
+    DestroyConfirmationInput(String),
+    DestroyForceUnmount(bool),
+    DestroyWithSnapshots(bool),
+    EnvironmentDestroyed(zvariant::OwnedObjectPath, String, Result<(), AppError>),
+    MountReadOnly(bool),
+    Mount(zvariant::OwnedObjectPath),
+    Mounted(zvariant::OwnedObjectPath, Result<String, AppError>),
+    OpenMountpoint(String),
+    Unmount(zvariant::OwnedObjectPath, bool),
+    Unmounted(zvariant::OwnedObjectPath, Result<String, AppError>),
+    SnapshotsDestroyed(
+        zvariant::OwnedObjectPath,
+        Vec<(String, Result<(), AppError>)>,
+    ),
+    Connected(Arc<dyn Backend>),
     Added(BootEnvironmentObject),
     Removed(zvariant::OwnedObjectPath),
+    PropertiesChanged(zvariant::OwnedObjectPath, BootEnvironmentChanges),
     BootEnvironmentsModified,
+    ReloadBootEnvironments,
+    ServiceOwnerChanged(bool),
+    ReplayPending,
+    CancelPending(u64),
+    PendingExpired(u64),
+    CanReboot(bool),
+    RequestReboot,
+    RebootTick(u64),
+    InhibitorsLoaded(u64, Vec<Inhibitor>),
+    CancelReboot,
+    RebootNow,
+    KeepTrial,
+    EndTrial,
+    UpdateConfig(Config),
+    CloseSettings,
+    SetNotifyBootChanges(bool),
+    SetNotifyEnvironmentChanges(bool),
+    NotifierReady(Arc<dyn Notifier>),
+    NotifyChanges,
+    NotificationSent(u32, bool, Vec<(String, Message)>),
+    NotificationAction(u32, String),
+    ShowRebootCountdown,
+}
+
+/// How long to wait for further changes before reloading all boot
+/// environments, so that bursts of signals cause a single reload.
+const RELOAD_DELAY: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// An action that shows an error banner, optionally with a retry button.
+fn error_action(
+    summary: String,
+    error: impl Into<AppError>,
+    retry: Option<Message>,
+) -> cosmic::Action<Message> {
+    cosmic::Action::App(Message::ShowError(
+        summary,
+        error.into(),
+        retry.map(Box::new),
+    ))
+}
+
+/// Spawn a task to connect to the backend: normally the boot environment
+/// service on the system bus, or an in-memory demo if `BACKEND_ENV` asks for
+/// one.
+fn connect_task() -> Task<cosmic::Action<Message>> {
+    if std::env::var(BACKEND_ENV).is_ok_and(|backend| backend == "memory") {
+        tracing::info!("Using in-memory demo backend");
+        let backend: Arc<dyn Backend> = Arc::new(MemoryBackend::demo());
+        return Task::done(cosmic::Action::App(Message::Connected(backend)));
+    }
+
+    Task::perform(DBusBackend::system(), |result| match result {
+        Ok(backend) => cosmic::Action::App(Message::Connected(Arc::new(backend))),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to connect to D-Bus");
+            cosmic::Action::App(Message::ConnectFailed(e))
+        }
+    })
+}
+
+/// How long to wait before reconnecting after a number of failures in a row.
+///
+/// The delay is spread over the upper half of the backoff by `jitter`, from 0
+/// to 1, so that applets in every session don't all retry at once after the
+/// bus restarts.
+fn reconnect_delay(failures: u32, jitter: f64) -> std::time::Duration {
+    let backoff = RECONNECT_DELAY
+        .saturating_mul(1 << failures.min(16))
+        .min(MAX_RECONNECT_DELAY);
+    backoff.mul_f64(0.5 + jitter.clamp(0.0, 1.0) / 2.0)
 }
 
-/// Query boot environments from D-Bus using the provided connection
-async fn load_boot_environments(
-    connection: &zbus::Connection,
-) -> Result<Vec<BootEnvironmentObject>, zbus::Error> {
-    // Get the ObjectManager to list all boot environment objects
-    let object_manager = ObjectManagerProxy::builder(connection)
-        .destination("ca.kamacite.BootEnvironments1")?
-        .path("/ca/kamacite/BootEnvironments")?
-        .build()
-        .await?;
+/// A random number from 0 to 1, good enough for spreading out retries.
+fn jitter() -> f64 {
+    use std::hash::{BuildHasher, Hasher};
 
-    let mut environments = Vec::new();
-    for (path, interfaces) in object_manager.get_managed_objects().await? {
-        if let Some(props) = interfaces.get("ca.kamacite.BootEnvironment") {
-            let env = BootEnvironmentObject::from_properties(path, props)?;
-            environments.push(env);
+    // Each `RandomState` is seeded differently.
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Schedule another attempt to connect after a number of failures in a row.
+fn reconnect_task(failures: u32) -> Task<cosmic::Action<Message>> {
+    let delay = reconnect_delay(failures, jitter());
+    tracing::info!(?delay, failures, "Reconnecting later");
+    Task::perform(tokio::time::sleep(delay), move |()| {
+        cosmic::Action::App(Message::RetryConnect(failures))
+    })
+}
+
+/// Spawn a task to connect to the notification server on the session bus, or
+/// to pretend to if `BACKEND_ENV` asks for an in-memory demo.
+fn notifier_task() -> Task<cosmic::Action<Message>> {
+    if std::env::var(BACKEND_ENV).is_ok_and(|backend| backend == "memory") {
+        let notifier: Arc<dyn Notifier> = Arc::new(MemoryNotifier::default());
+        return Task::done(cosmic::Action::App(Message::NotifierReady(notifier)));
+    }
+    Task::perform(DBusNotifier::session(), |result| match result {
+        Ok(notifier) => cosmic::Action::App(Message::NotifierReady(Arc::new(notifier))),
+        Err(e) => {
+            // Notifications are a nicety; carry on without them.
+            tracing::warn!(error = ?e, "Failed to connect to the session bus");
+            cosmic::Action::None
+        }
+    })
+}
+
+/// Spawn a task to show a notification, replacing an earlier one if given.
+/// Each of `actions` is a button, given as an action key, a label and the
+/// message it sends. `changes` marks notifications about changes.
+fn notify_task(
+    notifier: Arc<dyn Notifier>,
+    summary: String,
+    body: String,
+    actions: Vec<(&'static str, String, Message)>,
+    replaces: Option<u32>,
+    changes: bool,
+) -> Task<cosmic::Action<Message>> {
+    let (buttons, actions): (Vec<_>, Vec<_>) = actions
+        .into_iter()
+        .map(|(key, label, message)| ((key.to_string(), label), (key.to_string(), message)))
+        .unzip();
+    let notification = Notification {
+        summary,
+        body,
+        actions: buttons,
+        replaces,
+    };
+    Task::perform(
+        async move { notifier.notify(notification).await },
+        move |result| match result {
+            Ok(id) => cosmic::Action::App(Message::NotificationSent(id, changes, actions)),
+            Err(e) => {
+                tracing::warn!(error = ?e, "Failed to show a notification");
+                cosmic::Action::None
+            }
+        },
+    )
+}
+
+/// Wait a second, then advance a reboot countdown.
+fn reboot_tick_task(id: u64) -> Task<cosmic::Action<Message>> {
+    Task::perform(
+        tokio::time::sleep(std::time::Duration::from_secs(1)),
+        move |()| cosmic::Action::App(Message::RebootTick(id)),
+    )
+}
+
+/// Block shutting down and sleeping until the returned lock is dropped, so
+/// that a long-running operation isn't cut short. Failing to isn't worth
+/// abandoning the operation over.
+async fn inhibit_shutdown(backend: &dyn Backend, why: String) -> Option<ShutdownLock> {
+    match backend.inhibit_shutdown(&fl!("inhibit-who"), &why).await {
+        Ok(lock) => Some(lock),
+        Err(e) => {
+            tracing::warn!(error = ?e, why, "Failed to block shutdown");
+            None
         }
     }
+}
 
-    // Sort by creation time.
-    environments.sort_by(|a, b| a.created.cmp(&b.created));
+/// Destroy snapshots of a boot environment one at a time, returning the
+/// outcome for each snapshot
+async fn destroy_snapshots(
+    backend: &dyn Backend,
+    path: &zvariant::OwnedObjectPath,
+    snapshots: Vec<String>,
+) -> Vec<(String, Result<(), AppError>)> {
+    let mut results = Vec::with_capacity(snapshots.len());
+    for snapshot in snapshots {
+        // Carry on after a failure, so that one bad snapshot doesn't prevent
+        // the others from being cleaned up.
+        let result = backend.destroy_snapshot(path, &snapshot).await;
+        results.push((snapshot, result));
+    }
+    results
+}
 
-    Ok(environments)
+/// The directory under which the applet mounts boot environments for the
+/// current user.
+///
+/// This has to be somewhere only the user can write to, as the service mounts
+/// as root wherever it is told. Falling back to a fixed name in a shared
+/// directory such as `/tmp` would let another user plant a symlink there, so
+/// mounting is refused when there is no runtime directory instead.
+fn mount_root() -> Result<std::path::PathBuf, AppError> {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => {
+            Ok(std::path::PathBuf::from(dir).join("cosmic-applet-boot-environment"))
+        }
+        _ => Err(AppError::Other(fl!("error-no-runtime-dir"))),
+    }
 }
 
-/// Activate a boot environment by its D-Bus object path using the provided connection
-async fn activate_boot_environment(
-    connection: &zbus::Connection,
+/// Mount a boot environment at a directory created for it, returning the
+/// mountpoint
+async fn mount_boot_environment(
+    backend: &dyn Backend,
     path: &zvariant::OwnedObjectPath,
-) -> Result<(), zbus::Error> {
-    // Create a proxy for this boot environment
-    let proxy = BootEnvironmentProxy::builder(connection)
-        .path(path)?
-        .build()
-        .await?;
-
-    // Activate it temporarily.
-    proxy.activate(true).await?;
-    Ok(())
+    mountpoint: std::path::PathBuf,
+    read_only: bool,
+) -> Result<String, AppError> {
+    tokio::fs::create_dir_all(&mountpoint)
+        .await
+        .map_err(|e| AppError::Other(e.to_string()))?;
+    let mountpoint = mountpoint.to_string_lossy().into_owned();
+
+    backend.mount(path, &mountpoint, read_only).await?;
+    Ok(mountpoint)
+}
+
+/// A default, timestamped name for a new snapshot.
+fn default_snapshot_name() -> String {
+    chrono::Local::now().format("%Y-%m-%d-%H:%M:%S").to_string()
+}
+
+/// How to show a size in each binary unit, from bytes up, given the number
+/// already formatted for the user's language. Translations control the unit
+/// names and how they are laid out.
+const SIZE_UNITS: [fn(String) -> String; 6] = [
+    |value| fl!("size-bytes", value = value),
+    |value| fl!("size-kib", value = value),
+    |value| fl!("size-mib", value = value),
+    |value| fl!("size-gib", value = value),
+    |value| fl!("size-tib", value = value),
+    |value| fl!("size-pib", value = value),
+];
+
+/// Format a size in bytes for display, using binary units and the number
+/// format of the user's language.
+fn format_size(bytes: u64) -> String {
+    let bytes = u128::from(bytes);
+    let mut unit = 0;
+    let mut divisor = 1;
+    let mut tenths = bytes * 10;
+    // Round to a tenth before choosing the unit, so that a size just short of
+    // the next unit doesn't come out as 1024.0 of this one.
+    while tenths >= 1024 * 10 && unit + 1 < SIZE_UNITS.len() {
+        unit += 1;
+        divisor *= 1024;
+        tenths = (bytes * 10 + divisor / 2) / divisor;
+    }
+
+    let value = if unit == 0 {
+        Decimal::from(bytes)
+    } else {
+        let mut value = Decimal::from(tenths);
+        value.multiply_pow10(-1);
+        value
+    };
+    SIZE_UNITS[unit](i18n::format_decimal(&value))
+}
+
+/// Format a Unix timestamp in the local timezone for display.
+fn format_timestamp(timestamp: i64) -> String {
+    chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| {
+            dt.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
 }
 
 /// Create a COSMIC application from the app model
@@ -183,23 +856,83 @@ impl cosmic::Application for AppModel {
         core: cosmic::Core,
         _flags: Self::Flags,
     ) -> (Self, Task<cosmic::Action<Self::Message>>) {
+        let config_handler = cosmic_config::Config::new(Self::APP_ID, Config::VERSION)
+            .map_err(|e| tracing::error!(error = ?e, "Failed to open settings"))
+            .ok();
+        let config = config_handler
+            .as_ref()
+            .map(|handler| {
+                Config::get_entry(handler).unwrap_or_else(|(errors, config)| {
+                    for error in errors {
+                        tracing::error!(?error, "Failed to load a setting");
+                    }
+                    config
+                })
+            })
+            .unwrap_or_default();
+        let state_handler = cosmic_config::Config::new_state(Self::APP_ID, State::VERSION)
+            .map_err(|e| tracing::error!(error = ?e, "Failed to open saved state"))
+            .ok();
+        let state = state_handler
+            .as_ref()
+            .map(|handler| {
+                State::get_entry(handler).unwrap_or_else(|(errors, state)| {
+                    for error in errors {
+                        tracing::error!(?error, "Failed to load saved state");
+                    }
+                    state
+                })
+            })
+            .unwrap_or_default();
+
         // Construct the app model with the runtime's core.
         let app = AppModel {
             core,
             popup: None,
             // Start with empty list; will be populated from D-Bus
             environments: Vec::new(),
-            conn: None,
+            snapshots: HashMap::new(),
+            expanded: None,
+            selected_snapshots: HashSet::new(),
+            snapshot_failures: Vec::new(),
+            snapshot_name: default_snapshot_name(),
+            snapshot_description: String::new(),
+            snapshot_in_progress: false,
+            last_snapshot: None,
+            confirmation: None,
+            editor: None,
+            mount_read_only: true,
+            mount_in_progress: None,
+            unmount_failed: None,
+            service_available: true,
+            service_generation: 0,
+            reload_pending: false,
+            banners: Vec::new(),
+            next_banner_id: 0,
+            connection: ConnectionState::Connecting { failures: 0 },
+            pending: Vec::new(),
+            next_pending_id: 0,
+            can_reboot: false,
+            reboot: None,
+            next_reboot_id: 0,
+            first_load_done: false,
+            trial: None,
+            trial_notified: false,
+            config,
+            config_handler,
+            state,
+            state_handler,
+            settings_open: false,
+            notifier: None,
+            notified: None,
+            notify_pending: false,
+            requested_target: None,
+            notifications: Vec::new(),
+            backend: None,
         };
 
-        // Spawn a task to open the D-Bus connection.
-        let task = Task::perform(zbus::Connection::system(), |result| match result {
-            Ok(conn) => cosmic::Action::App(Message::Connected(conn)),
-            Err(e) => {
-                tracing::error!(error = ?e, "Failed to connect to D-Bus");
-                cosmic::Action::None
-            }
-        });
+        // Spawn tasks to connect to the backend and the notification server.
+        let task = Task::batch([connect_task(), notifier_task()]);
 
         (app, task)
     }
@@ -225,8 +958,82 @@ impl cosmic::Application for AppModel {
             space_xxs, space_s, ..
         } = theme::active().cosmic().spacing;
 
-        // Build the column starting with boot environment rows
-        let mut content = column![];
+        // The settings don't need the service.
+        if self.settings_open {
+            return self
+                .core
+                .applet
+                .popup_container(self.view_settings())
+                .into();
+        }
+
+        // There is nothing to show or do without the service.
+        let status = match &self.connection {
+            ConnectionState::Connecting { .. } => {
+                Some((fl!("connecting"), fl!("connecting-details"), false))
+            }
+            ConnectionState::Waiting { error, .. } => Some((
+                fl!("disconnected"),
+                fl!("disconnected-details", reason = error.message()),
+                true,
+            )),
+            ConnectionState::Connected if !self.service_available => Some((
+                fl!("service-unavailable"),
+                fl!("service-unavailable-details"),
+                false,
+            )),
+            ConnectionState::Connected => None,
+        };
+        if let Some((heading, details, reconnect)) = status {
+            let mut content = column![
+                self.view_banners(),
+                padded_control(
+                    row![
+                        cosmic::widget::icon::from_name("dialog-warning-symbolic").size(24),
+                        column![text::heading(heading), text::caption(details)].width(Length::Fill),
+                    ]
+                    .align_y(Alignment::Center)
+                    .spacing(space_s)
+                ),
+            ]
+            .align_x(Alignment::Start)
+            .padding([8, 0]);
+            if reconnect {
+                content = content
+                    .push(menu_button(text::body(fl!("reconnect"))).on_press(Message::Connect));
+            }
+            content = content
+                .push(padded_control(divider::horizontal::default()).padding([space_xxs, space_s]))
+                .push(
+                    menu_button(text::body(fl!("boot-settings")))
+                        .on_press(Message::BootSettingsClicked),
+                );
+
+            return self.core.applet.popup_container(content).into();
+        }
+
+        // Destructive actions take over the whole popup until they are
+        // confirmed or cancelled.
+        if let Some(confirmation) = &self.confirmation {
+            return self
+                .core
+                .applet
+                .popup_container(self.view_confirmation(confirmation))
+                .into();
+        }
+
+        // So does a reboot, until it happens or is cancelled.
+        if let Some(countdown) = &self.reboot {
+            return self
+                .core
+                .applet
+                .popup_container(self.view_reboot(countdown))
+                .into();
+        }
+
+        // Build the column starting with any errors, then boot environment
+        // rows.
+        let mut content = column![self.view_banners()];
 
         // Display a summary of the active boot environment at the top.
         if let Some(active_env) = self.environments.iter().find(|e| e.active) {
@@ -245,6 +1052,12 @@ impl cosmic::Application for AppModel {
                 .align_y(Alignment::Center)
                 .spacing(space_s),
             ));
+
+            if let Some(trial) = self.trial_environment() {
+                content = content.push(self.view_trial(trial));
+            }
+
+            content = content.push(self.view_create_snapshot(active_env));
         } else {
             content = content.push(padded_control(
                 row![text::body(fl!("no-active-boot-env"))]
@@ -253,49 +1066,122 @@ impl cosmic::Application for AppModel {
             ));
         }
 
+        // The total space used by all boot environments, to help decide
+        // whether any are worth cleaning up.
+        if !self.environments.is_empty() {
+            content = content.push(padded_control(text::caption(fl!(
+                "total-space",
+                count = self.environments.len(),
+                space = format_size(self.total_space())
+            ))));
+        }
+
         // Divider.
         content = content
             .push(padded_control(divider::horizontal::default()).padding([space_xxs, space_s]));
 
-        // A dropdown for activating boot environments, if they exist.
-        let dropdown_labels: Vec<String> = self
-            .environments
-            .iter()
-            .map(|env| {
-                // Build the label - either description or name
-                if let Some(desc) = &env.description {
-                    format!("{} ({})", desc, env.name)
-                } else {
-                    env.name.clone()
-                }
-            })
-            .collect();
+        // Dropdowns for activating boot environments, if they exist: one for
+        // the next boot only and one to change the default.
+        let dropdown_labels = self.dropdown_labels();
 
         if !dropdown_labels.is_empty() {
-            // Don't distinguish between temporary and permanent activations.
-            let selected_idx = self
-                .environments
-                .iter()
-                .position(|e| e.boot_once)
-                .or(self.environments.iter().position(|e| e.next_boot));
+            let once_idx = self.boot_once_idx();
+            let default_idx = self.next_boot_idx();
 
             let paths: Vec<zvariant::OwnedObjectPath> = self
                 .environments
                 .iter()
                 .map(|env| env.path.clone())
                 .collect();
+            let once_paths = paths.clone();
+
+            content = content.push(padded_control(
+                row![
+                    text::body(fl!("reboot-into-once")).width(Length::Fill),
+                    dropdown(dropdown_labels.clone(), once_idx, move |idx| {
+                        Message::ActivateEnvironment(once_paths[idx].clone(), true)
+                    })
+                ]
+                .align_y(Alignment::Center)
+                .spacing(space_s),
+            ));
 
             content = content.push(padded_control(
                 row![
-                    text::body(fl!("reboot-into")).width(Length::Fill),
-                    dropdown(dropdown_labels, selected_idx, move |idx| {
-                        Message::ActivateEnvironment(paths[idx].clone())
+                    text::body(fl!("make-default")).width(Length::Fill),
+                    dropdown(dropdown_labels, default_idx, move |idx| {
+                        Message::ActivateEnvironment(paths[idx].clone(), false)
                     })
                 ]
                 .align_y(Alignment::Center)
                 .spacing(space_s),
             ));
 
+            // Offer to start using the chosen boot environment right away.
+            if let Some(target) = self.reboot_target().filter(|_| self.can_reboot) {
+                content = content.push(padded_control(
+                    row![
+                        text::body(fl!("reboot-required", name = target.label()))
+                            .width(Length::Fill),
+                        button::suggested(fl!("reboot-now")).on_press(Message::RequestReboot),
+                    ]
+                    .align_y(Alignment::Center)
+                    .spacing(space_s),
+                ));
+            }
+
+            // Divider.
+            content = content
+                .push(padded_control(divider::horizontal::default()).padding([space_xxs, space_s]));
+        }
+
+        // A list of boot environments, each of which can be expanded to show
+        // its snapshots.
+        for env in &self.environments {
+            let expanded = self.expanded.as_ref() == Some(&env.path);
+            let chevron = if expanded {
+                "go-up-symbolic"
+            } else {
+                "go-down-symbolic"
+            };
+
+            match &self.editor {
+                Some(editor) if editor.path == env.path && editor.field == EditorField::Name => {
+                    content = content.push(self.view_editor(editor));
+                }
+                _ => {
+                    let mut details = env.status_labels();
+                    details.push(format_size(env.space));
+                    let heading =
+                        column![text::body(env.label()), text::caption(details.join(" · ")),];
+
+                    content = content.push(
+                        menu_button(
+                            row![
+                                heading.width(Length::Fill),
+                                cosmic::widget::icon::from_name(chevron).size(16),
+                            ]
+                            .align_y(Alignment::Center)
+                            .spacing(space_s),
+                        )
+                        .on_press(Message::ToggleExpanded(env.path.clone())),
+                    );
+                }
+            }
+
+            if let Some(editor) = &self.editor {
+                if editor.path == env.path && editor.field == EditorField::Description {
+                    content = content.push(self.view_editor(editor));
+                }
+            }
+
+            if expanded {
+                content = content.push(self.view_environment_actions(env));
+                content = content.push(self.view_snapshots(env));
+            }
+        }
+
+        if !self.environments.is_empty() {
             // Divider.
             content = content
                 .push(padded_control(divider::horizontal::default()).padding([space_xxs, space_s]));
@@ -318,23 +1204,52 @@ impl cosmic::Application for AppModel {
     /// emit messages to the application through a channel. They are started at the
     /// beginning of the application, and persist through its lifetime.
     fn subscription(&self) -> Subscription<Self::Message> {
-        struct ObjectManagerSub;
-        struct PropertiesChangedSub;
+        struct ConnectionSub;
+        struct ServiceSub;
+        struct EventsSub;
+        struct NotifierSub;
+
+        // Settings may be changed by another instance of the applet.
+        let config = self
+            .core
+            .watch_config::<Config>(Self::APP_ID)
+            .map(|update| {
+                for error in update.errors {
+                    tracing::error!(?error, "Failed to load a setting");
+                }
+                Message::UpdateConfig(update.config)
+            });
+        let mut subscriptions = vec![config];
 
-        if let Some(ref conn) = self.conn {
-            Subscription::batch(vec![
+        if let Some(notifier) = &self.notifier {
+            subscriptions.push(Subscription::run_with_id(
+                std::any::TypeId::of::<NotifierSub>(),
+                notifier
+                    .actions()
+                    .map(|(id, key)| Message::NotificationAction(id, key)),
+            ));
+        }
+
+        if let Some(ref backend) = self.backend {
+            // The event subscription is keyed on the service generation so
+            // that it is restarted whenever the service is.
+            subscriptions.extend([
                 Subscription::run_with_id(
-                    std::any::TypeId::of::<ObjectManagerSub>(),
-                    object_manager_stream(conn.clone()),
+                    std::any::TypeId::of::<ConnectionSub>(),
+                    backend.closed().map(Message::ConnectionLost).into_stream(),
                 ),
                 Subscription::run_with_id(
-                    std::any::TypeId::of::<PropertiesChangedSub>(),
-                    properties_changed_stream(conn.clone()),
+                    std::any::TypeId::of::<ServiceSub>(),
+                    backend.watch_service().map(Message::ServiceOwnerChanged),
                 ),
-            ])
-        } else {
-            Subscription::none()
+                Subscription::run_with_id(
+                    (std::any::TypeId::of::<EventsSub>(), self.service_generation),
+                    backend.watch().map(event_message),
+                ),
+            ]);
         }
+
+        Subscription::batch(subscriptions)
     }
 
     /// Handles messages emitted by the application and its widgets.
@@ -344,254 +1259,1997 @@ impl cosmic::Application for AppModel {
     fn update(&mut self, message: Self::Message) -> Task<cosmic::Action<Self::Message>> {
         match message {
             Message::BootSettingsClicked => {
-                // Placeholder: would open boot settings configuration
-                tracing::info!("Opening boot settings");
-            }
-            Message::Connected(conn) => {
-                tracing::info!(
-                    unique_name = conn
-                        .unique_name()
-                        .map(std::string::ToString::to_string)
-                        .unwrap_or_default(),
-                    "Connected to system bus"
-                );
-                // Store the active connection and start a task to load existing
-                // boot environments.
-                self.conn = Some(conn.clone());
-                return Task::perform(
-                    async move { load_boot_environments(&conn).await },
-                    |result| match result {
-                        Ok(environments) => {
-                            cosmic::Action::App(Message::BootEnvironmentsLoaded(environments))
-                        }
-                        Err(e) => {
-                            tracing::error!(error = ?e, "Failed to load boot environments");
-                            cosmic::Action::None
-                        }
-                    },
-                );
+                self.settings_open = true;
             }
-            Message::BootEnvironmentsLoaded(environments) => {
-                tracing::info!(count = environments.len(), "Loaded boot environments");
-                self.environments = environments;
+            Message::CloseSettings => {
+                self.settings_open = false;
             }
-            Message::Added(env) => {
-                tracing::info!(path = ?env.path, name = %env.name, "Boot environment added");
-                // No need to re-sort, we know the new environment is the most recent.
-                self.environments.push(env);
+            Message::UpdateConfig(config) => {
+                self.config = config;
             }
-            Message::Removed(path) => {
-                tracing::info!(?path, "Boot environment removed");
-                self.environments.retain(|env| env.path != path);
+            Message::SetNotifyBootChanges(enabled) => {
+                self.change_config(|config| config.notify_boot_changes = enabled);
             }
-            Message::BootEnvironmentsModified => {
-                if let Some(conn) = self.conn.clone() {
-                    return Task::perform(
-                        async move { load_boot_environments(&conn).await },
-                        |result| match result {
-                            Ok(environments) => {
-                                cosmic::Action::App(Message::BootEnvironmentsLoaded(environments))
-                            }
-                            Err(e) => {
-                                tracing::error!(error = ?e, "Failed to reload boot environments");
-                                cosmic::Action::None
-                            }
-                        },
-                    );
+            Message::SetNotifyEnvironmentChanges(enabled) => {
+                self.change_config(|config| config.notify_environment_changes = enabled);
+            }
+            Message::NotifierReady(notifier) => {
+                self.notifier = Some(notifier);
+                // Changes made before now aren't worth mentioning.
+                if self.notified.is_some() && self.ready_backend().is_some() {
+                    self.notified = Some(BootTargets::new(&self.environments));
                 }
+                return self.notify_trial_task();
             }
-            Message::ActivateEnvironment(path) => {
-                if let Some(conn) = self.conn.clone() {
-                    let path_ref = path.clone();
-                    return Task::perform(
-                        async move { activate_boot_environment(&conn, &path_ref).await },
-                        move |result| {
-                            match result {
-                                Ok(()) => tracing::info!(
-                                    path = path.to_string(),
-                                    "Temporarily activated boot environment"
-                                ),
-                                Err(e) => {
-                                    tracing::error!(path = path.to_string(), error = ?e, "Failed to activate boot environment");
-                                }
-                            }
-                            cosmic::Action::None
-                        },
-                    );
+            Message::NotifyChanges => {
+                self.notify_pending = false;
+                // Wait until the boot environments have been reloaded.
+                if self.ready_backend().is_none() {
+                    return Task::none();
+                }
+                let current = BootTargets::new(&self.environments);
+                let Some(mut previous) = self.notified.replace(current.clone()) else {
+                    return Task::none();
+                };
+                if let Some((path, temporary)) = &self.requested_target {
+                    if previous.take_in_request(path, *temporary, &current) {
+                        self.requested_target = None;
+                    }
                 }
-                // It should never be possible to send this message without an
-                // active D-Bus connection.
-                unreachable!("no D-Bus connection available");
+                return self.notify_changes_task(&previous, &current);
             }
-            Message::TogglePopup => {
-                return if let Some(p) = self.popup.take() {
-                    destroy_popup(p)
-                } else {
-                    let new_id = Id::unique();
-                    self.popup.replace(new_id);
-                    let popup_settings = self.core.applet.get_popup_settings(
-                        self.core.main_window_id().unwrap(),
-                        new_id,
-                        None,
-                        None,
-                        None,
-                    );
-                    get_popup(popup_settings)
+            Message::NotificationSent(id, changes, actions) => {
+                self.notifications.retain(|sent| sent.id != id);
+                if self.notifications.len() >= MAX_NOTIFICATIONS {
+                    self.notifications.remove(0);
                 }
+                self.notifications.push(SentNotification {
+                    id,
+                    changes,
+                    actions,
+                });
             }
-            Message::PopupClosed(id) => {
-                if self.popup.as_ref() == Some(&id) {
-                    self.popup = None;
+            Message::NotificationAction(id, key) => {
+                let message = self
+                    .notifications
+                    .iter()
+                    .find(|sent| sent.id == id)
+                    .and_then(|sent| sent.actions.iter().find(|(k, _)| *k == key))
+                    .map(|(_, message)| message.clone());
+                if let Some(message) = message {
+                    return self.update(message);
                 }
             }
-        }
-        Task::none()
-    }
+            Message::ShowRebootCountdown => {
+                // The countdown is shown in the popup.
+                // Opening the popup checks whether rebooting is still
+                // allowed, which may have changed since the notification
+                // was shown.
+                let open = if self.popup.is_none() {
+                    self.update(Message::TogglePopup)
+                } else {
+                    self.can_reboot_task()
+                };
+                return Task::batch([open, self.update(Message::RequestReboot)]);
+            }
+            Message::Connect => {
+                // Connect now rather than waiting for the next retry, but
+                // keep backing off if this fails too.
+                if let ConnectionState::Waiting { failures, .. } = self.connection {
+                    self.connection = ConnectionState::Connecting { failures };
+                    return connect_task();
+                }
+            }
+            Message::RetryConnect(failures) => {
+                // Ignore retries that have been overtaken by a manual one.
+                if matches!(&self.connection, ConnectionState::Waiting { failures: f, .. } if *f == failures)
+                {
+                    self.connection = ConnectionState::Connecting { failures };
+                    return connect_task();
+                }
+            }
+            Message::ConnectFailed(error) => {
+                if let ConnectionState::Connecting { failures } = self.connection {
+                    let failures = failures.saturating_add(1);
+                    self.connection = ConnectionState::Waiting { failures, error };
+                    return reconnect_task(failures);
+                }
+            }
+            Message::ConnectionLost(error) => {
+                if self.connection != ConnectionState::Connected {
+                    return Task::none();
+                }
+                tracing::warn!(error = ?error, "Lost the connection to the system bus");
+                // Drop the backend, which stops its subscriptions, and forget
+                // everything learned through it. Actions requested until it
+                // is back are held.
+                self.backend = None;
+                self.service_generation = self.service_generation.wrapping_add(1);
+                self.forget_environments();
+                self.connection = ConnectionState::Waiting { failures: 0, error };
+                return reconnect_task(0);
+            }
+            Message::ShowError(summary, error, retry) => {
+                self.show_error(summary, error, retry.map(|retry| *retry));
+            }
+            Message::DismissBanner(id) => {
+                self.banners.retain(|banner| banner.id != id);
+            }
+            Message::RetryBanner(id) => {
+                let Some(idx) = self.banners.iter().position(|banner| banner.id == id) else {
+                    return Task::none();
+                };
+                if let Some(retry) = self.banners.remove(idx).retry {
+                    return self.update(retry);
+                }
+            }
+            Message::Connected(backend) => {
+                // Store the backend and start a task to load existing boot
+                // environments.
+                self.backend = Some(backend);
+                self.connection = ConnectionState::Connected;
+                return Task::batch([
+                    self.load_environments_task()
+                        .chain(Task::done(cosmic::Action::App(Message::ReplayPending))),
+                    self.can_reboot_task(),
+                ]);
+            }
+            Message::ServiceOwnerChanged(true) => {
+                tracing::info!("Boot environment service is available");
+                // Start over against the new owner.
+                self.service_available = true;
+                self.service_generation = self.service_generation.wrapping_add(1);
+                return self
+                    .load_environments_task()
+                    .chain(Task::done(cosmic::Action::App(Message::ReplayPending)));
+            }
+            Message::ServiceOwnerChanged(false) => {
+                if self.service_available {
+                    tracing::warn!("Boot environment service is unavailable");
+                }
+                self.service_available = false;
+                self.forget_environments();
+            }
+            Message::ReplayPending => {
+                if self.ready_backend().is_none() || self.pending.is_empty() {
+                    return Task::none();
+                }
+                tracing::info!(count = self.pending.len(), "Performing pending actions");
+                let mut tasks = Vec::with_capacity(self.pending.len());
+                for action in std::mem::take(&mut self.pending) {
+                    // The boot environments have just been reloaded, and may
+                    // no longer be what the user agreed to act on.
+                    if let Some(error) = action
+                        .confirmation
+                        .as_ref()
+                        .and_then(|confirmation| confirmation.refusal(&self.environments))
+                    {
+                        tracing::warn!(action = action.label, error = ?error, "Dropping an outdated pending action");
+                        self.show_error(fl!("error-pending", action = action.label), error, None);
+                        continue;
+                    }
+                    if action.confirmation.is_some() {
+                        self.confirmation = action.confirmation;
+                    }
+                    if action.editor.is_some() {
+                        self.editor = action.editor;
+                    }
+                    tasks.push(self.update(action.message));
+                }
+                return Task::batch(tasks);
+            }
+            Message::CancelPending(id) => {
+                self.pending.retain(|action| action.id != id);
+            }
+            Message::PendingExpired(id) => {
+                let Some(idx) = self.pending.iter().position(|action| action.id == id) else {
+                    return Task::none();
+                };
+                let action = self.pending.remove(idx);
+                tracing::warn!(action = action.label, "Gave up on a pending action");
+                self.show_error(
+                    fl!("error-pending", action = action.label),
+                    AppError::Timeout,
+                    Some(action.message),
+                );
+            }
+            Message::CanReboot(can_reboot) => {
+                self.can_reboot = can_reboot;
+                if !can_reboot {
+                    // The reboot would only fail at the end of the countdown.
+                    self.reboot = None;
+                }
+            }
+            Message::RequestReboot => {
+                let Some(label) = self.reboot_target().map(BootEnvironmentObject::label) else {
+                    return Task::none();
+                };
+                let id = self.next_reboot_id;
+                self.next_reboot_id = self.next_reboot_id.wrapping_add(1);
+                self.reboot = Some(RebootCountdown {
+                    id,
+                    label,
+                    remaining: REBOOT_COUNTDOWN,
+                    inhibitors: Vec::new(),
+                });
+                return Task::batch([reboot_tick_task(id), self.load_inhibitors_task(id)]);
+            }
+            Message::RebootTick(id) => {
+                let Some(countdown) = self.reboot.as_mut().filter(|c| c.id == id) else {
+                    return Task::none();
+                };
+                countdown.remaining = countdown.remaining.saturating_sub(1);
+                if countdown.remaining == 0 {
+                    return self.update(Message::RebootNow);
+                }
+                return Task::batch([reboot_tick_task(id), self.load_inhibitors_task(id)]);
+            }
+            Message::InhibitorsLoaded(id, inhibitors) => {
+                if let Some(countdown) = self.reboot.as_mut().filter(|c| c.id == id) {
+                    countdown.inhibitors = inhibitors;
+                }
+            }
+            Message::CancelReboot => {
+                self.reboot = None;
+            }
+            Message::RebootNow => {
+                self.reboot = None;
+                let Some(backend) = self.backend.clone() else {
+                    return Task::none();
+                };
+                tracing::info!("Rebooting");
+                return Task::perform(
+                    async move { backend.reboot().await },
+                    |result| match result {
+                        Ok(()) => cosmic::Action::None,
+                        Err(e) => {
+                            tracing::error!(error = ?e, "Failed to reboot");
+                            error_action(fl!("error-reboot"), e, Some(Message::RebootNow))
+                        }
+                    },
+                );
+            }
+            Message::BootEnvironmentsLoaded(environments) => {
+                tracing::info!(count = environments.len(), "Loaded boot environments");
+                self.service_available = true;
+                self.environments = environments;
+
+                // Only a boot environment running when the user logs in can
+                // have been booted into temporarily: later on, it's just the
+                // default having been changed. Even then, the default may
+                // have been changed without rebooting, so it has to be the
+                // one last seen scheduled to boot once.
+                if !self.first_load_done {
+                    self.first_load_done = true;
+                    let scheduled = self.state.boot_once.as_deref();
+                    let scheduled = self
+                        .environments
+                        .iter()
+                        .find(|env| Some(env.path.as_str()) == scheduled);
+                    self.trial = scheduled
+                        .filter(|env| env.is_temporarily_booted())
+                        .map(|env| env.path.clone());
+                    if let Some(path) = &self.trial {
+                        tracing::info!(path = path.to_string(), "Booted temporarily");
+                    } else if !scheduled.is_some_and(|env| env.boot_once) {
+                        // Booting once has played out, or was overridden.
+                        self.change_state(|state| state.boot_once = None);
+                    }
+                }
+                self.remember_boot_once();
+
+                // Notify about what changed while the service was away, but
+                // not about what was there to begin with.
+                let notify = if self.notified.is_none() {
+                    self.notified = Some(BootTargets::new(&self.environments));
+                    self.notify_trial_task()
+                } else {
+                    self.notify_later()
+                };
+
+                // Forget about snapshots for environments that no longer
+                // exist, then refresh the rest.
+                let environments = &self.environments;
+                self.snapshots
+                    .retain(|path, _| environments.iter().any(|env| &env.path == path));
+                if self
+                    .expanded
+                    .as_ref()
+                    .is_some_and(|path| !environments.iter().any(|env| &env.path == path))
+                {
+                    self.set_expanded(None);
+                }
+                let mut tasks: Vec<_> = self
+                    .environments
+                    .iter()
+                    .map(|env| self.load_snapshots_task(env.path.clone()))
+                    .collect();
+                tasks.push(notify);
+                return Task::batch(tasks);
+            }
+            Message::SnapshotsLoaded(path, snapshots) => {
+                tracing::debug!(?path, count = snapshots.len(), "Loaded snapshots");
+                if self.environments.iter().any(|env| env.path == path) {
+                    if self.expanded.as_ref() == Some(&path) {
+                        // Drop selections for snapshots that are now gone.
+                        self.selected_snapshots
+                            .retain(|name| snapshots.iter().any(|s| &s.name == name));
+                    }
+                    self.snapshots.insert(path, snapshots);
+                }
+            }
+            Message::SnapshotNameInput(name) => {
+                self.snapshot_name = name;
+            }
+            Message::SnapshotDescriptionInput(description) => {
+                self.snapshot_description = description;
+            }
+            Message::CreateSnapshot => {
+                let Some(active_env) = self.environments.iter().find(|e| e.active) else {
+                    return Task::none();
+                };
+                let Some(backend) = self.ready_backend() else {
+                    let label = fl!("pending-snapshot", name = active_env.name.clone());
+                    return self.defer(label, Message::CreateSnapshot, None, None);
+                };
+                let path = active_env.path.clone();
+                let name = self.snapshot_name.trim().to_string();
+                let description = self.snapshot_description.trim().to_string();
+                self.snapshot_in_progress = true;
+                return Task::perform(
+                    async move {
+                        let result = backend.snapshot(&path, &name, &description).await;
+                        (path, result)
+                    },
+                    |(path, result)| cosmic::Action::App(Message::SnapshotCreated(path, result)),
+                );
+            }
+            Message::SnapshotCreated(path, result) => {
+                self.snapshot_in_progress = false;
+                match result {
+                    Ok(name) => {
+                        tracing::info!(path = path.to_string(), name, "Created snapshot");
+                        self.last_snapshot = Some(name);
+                        self.snapshot_name = default_snapshot_name();
+                        self.snapshot_description.clear();
+                        return self.load_snapshots_task(path);
+                    }
+                    Err(e) => {
+                        tracing::error!(path = path.to_string(), error = ?e, "Failed to create snapshot");
+                        self.show_error(
+                            fl!("error-create-snapshot"),
+                            e.into(),
+                            Some(Message::CreateSnapshot),
+                        );
+                    }
+                }
+            }
+            Message::RequestRollback(path, snapshot) => {
+                let Some(env) = self.environments.iter().find(|env| env.path == path) else {
+                    return Task::none();
+                };
+                let snapshots = self.snapshots.get(&path).map_or(&[][..], Vec::as_slice);
+                let Some(target) = snapshots.iter().find(|s| s.name == snapshot) else {
+                    return Task::none();
+                };
+                let (destroyed, maybe_destroyed) = later_snapshots(snapshots, target);
+                self.confirmation = Some(Confirmation::Rollback {
+                    path,
+                    snapshot,
+                    destroyed,
+                    maybe_destroyed,
+                    active: env.may_be_active(),
+                    override_active: false,
+                });
+            }
+            Message::OverrideActiveRollback(value) => {
+                if let Some(Confirmation::Rollback {
+                    override_active, ..
+                }) = &mut self.confirmation
+                {
+                    *override_active = value;
+                }
+            }
+            Message::CancelAction => {
+                self.confirmation = None;
+            }
+            Message::ConfirmAction => {
+                let Some(confirmation) = self.confirmation.take() else {
+                    return Task::none();
+                };
+                if !confirmation.can_confirm() {
+                    // Keep waiting for the user.
+                    self.confirmation = Some(confirmation);
+                    return Task::none();
+                }
+                let Some(backend) = self.ready_backend() else {
+                    let label = self.confirmation_label(&confirmation);
+                    return self.defer(label, Message::ConfirmAction, Some(confirmation), None);
+                };
+                // The boot environments may have changed while the user was
+                // deciding, so what they agreed to might no longer be safe.
+                if let Some(error) = confirmation.refusal(&self.environments) {
+                    let label = self.confirmation_label(&confirmation);
+                    tracing::warn!(action = label, error = ?error, "Refusing an outdated action");
+                    self.show_error(fl!("error-refused", action = label), error, None);
+                    return Task::none();
+                }
+                let why = self.inhibit_reason(&confirmation);
+                match confirmation {
+                    Confirmation::Rollback { path, snapshot, .. } => {
+                        return Task::perform(
+                            async move {
+                                let _lock = inhibit_shutdown(&*backend, why).await;
+                                let result = backend.rollback(&path, &snapshot).await;
+                                (path, snapshot, result)
+                            },
+                            |(path, snapshot, result)| {
+                                cosmic::Action::App(Message::RolledBack(path, snapshot, result))
+                            },
+                        );
+                    }
+                    Confirmation::DestroySnapshots {
+                        path, snapshots, ..
+                    } => {
+                        self.snapshot_failures.clear();
+                        return Task::perform(
+                            async move {
+                                let _lock = inhibit_shutdown(&*backend, why).await;
+                                let results = destroy_snapshots(&*backend, &path, snapshots).await;
+                                (path, results)
+                            },
+                            |(path, results)| {
+                                cosmic::Action::App(Message::SnapshotsDestroyed(path, results))
+                            },
+                        );
+                    }
+                    Confirmation::DestroyEnvironment {
+                        path,
+                        name,
+                        force_unmount,
+                        snapshots,
+                        ..
+                    } => {
+                        return Task::perform(
+                            async move {
+                                let _lock = inhibit_shutdown(&*backend, why).await;
+                                let result = backend.destroy(&path, force_unmount, snapshots).await;
+                                (path, name, result)
+                            },
+                            |(path, name, result)| {
+                                cosmic::Action::App(Message::EnvironmentDestroyed(
+                                    path, name, result,
+                                ))
+                            },
+                        );
+                    }
+                }
+            }
+            Message::RequestDestroyEnvironment(path) => {
+                let Some(env) = self.environments.iter().find(|env| env.path == path) else {
+                    return Task::none();
+                };
+                if !env.can_destroy() {
+                    tracing::warn!(
+                        path = path.to_string(),
+                        "Refusing to destroy a boot environment that is active, scheduled or not fully known"
+                    );
+                    return Task::none();
+                }
+                self.confirmation = Some(Confirmation::DestroyEnvironment {
+                    path,
+                    name: env.name.clone(),
+                    typed: String::new(),
+                    force_unmount: false,
+                    snapshots: false,
+                });
+            }
+            Message::DestroyConfirmationInput(value) => {
+                if let Some(Confirmation::DestroyEnvironment { typed, .. }) = &mut self.confirmation
+                {
+                    *typed = value;
+                }
+            }
+            Message::DestroyForceUnmount(value) => {
+                if let Some(Confirmation::DestroyEnvironment { force_unmount, .. }) =
+                    &mut self.confirmation
+                {
+                    *force_unmount = value;
+                }
+            }
+            Message::DestroyWithSnapshots(value) => {
+                if let Some(Confirmation::DestroyEnvironment { snapshots, .. }) =
+                    &mut self.confirmation
+                {
+                    *snapshots = value;
+                }
+            }
+            Message::EnvironmentDestroyed(path, name, result) => match result {
+                // The environment itself is dropped from the list when the
+                // InterfacesRemoved signal arrives.
+                Ok(()) => {
+                    tracing::info!(path = path.to_string(), name, "Destroyed boot environment");
+                }
+                Err(e) => {
+                    tracing::error!(path = path.to_string(), name, error = ?e, "Failed to destroy boot environment");
+                    self.show_error(
+                        fl!("error-destroy-environment", name = name),
+                        e.into(),
+                        None,
+                    );
+                }
+            },
+            Message::RolledBack(path, snapshot, result) => {
+                match result {
+                    Ok(()) => {
+                        tracing::info!(
+                            path = path.to_string(),
+                            snapshot,
+                            "Rolled back boot environment"
+                        );
+                    }
+                    Err(e) => {
+                        tracing::error!(path = path.to_string(), snapshot, error = ?e, "Failed to roll back boot environment");
+                        self.show_error(fl!("error-rollback", snapshot = snapshot), e.into(), None);
+                    }
+                }
+                return self.load_snapshots_task(path);
+            }
+            Message::SelectSnapshot(name, selected) => {
+                if selected {
+                    self.selected_snapshots.insert(name);
+                } else {
+                    self.selected_snapshots.remove(&name);
+                }
+            }
+            Message::RequestDestroySnapshots(names) => {
+                let Some(path) = self.expanded.clone() else {
+                    return Task::none();
+                };
+                let selected: Vec<&Snapshot> = self
+                    .snapshots
+                    .get(&path)
+                    .into_iter()
+                    .flatten()
+                    .filter(|s| names.contains(&s.name))
+                    .collect();
+                if !selected.is_empty() {
+                    let space = selected.iter().map(|s| s.space).sum();
+                    let snapshots = selected.into_iter().map(|s| s.name.clone()).collect();
+                    self.confirmation = Some(Confirmation::DestroySnapshots {
+                        path,
+                        snapshots,
+                        space,
+                    });
+                }
+            }
+            Message::SnapshotsDestroyed(path, results) => {
+                let mut failures = Vec::new();
+                for (snapshot, result) in results {
+                    match result {
+                        Ok(()) => {
+                            tracing::info!(path = path.to_string(), snapshot, "Destroyed snapshot");
+                            self.selected_snapshots.remove(&snapshot);
+                        }
+                        Err(e) => {
+                            tracing::error!(path = path.to_string(), snapshot, error = ?e, "Failed to destroy snapshot");
+                            failures.push((snapshot, e.message()));
+                        }
+                    }
+                }
+                if self.expanded.as_ref() == Some(&path) {
+                    self.snapshot_failures = failures;
+                }
+                return self.load_snapshots_task(path);
+            }
+            Message::StartRename(path) => {
+                if let Some(env) = self
+                    .environments
+                    .iter()
+                    .find(|env| env.path == path && env.name_known())
+                {
+                    self.editor = Some(Editor {
+                        path,
+                        field: EditorField::Name,
+                        value: env.name.clone(),
+                        in_progress: false,
+                        error: None,
+                    });
+                }
+            }
+            Message::StartDescribe(path) => {
+                if let Some(env) = self.environments.iter().find(|env| env.path == path) {
+                    self.editor = Some(Editor {
+                        path,
+                        field: EditorField::Description,
+                        value: env.description.clone().unwrap_or_default(),
+                        in_progress: false,
+                        error: None,
+                    });
+                }
+            }
+            Message::EditorInput(value) => {
+                if let Some(editor) = &mut self.editor {
+                    editor.value = value;
+                    editor.error = None;
+                }
+            }
+            Message::CancelEditor => {
+                self.editor = None;
+            }
+            Message::SubmitEditor => {
+                let Some(editor) = &self.editor else {
+                    return Task::none();
+                };
+                if editor.in_progress {
+                    return Task::none();
+                }
+                let Some(backend) = self.ready_backend() else {
+                    let name = self.environment_name(&editor.path);
+                    let label = match editor.field {
+                        EditorField::Name => fl!(
+                            "pending-rename",
+                            name = name,
+                            new_name = editor.value.trim().to_string()
+                        ),
+                        EditorField::Description => fl!("pending-describe", name = name),
+                    };
+                    let editor = self.editor.take();
+                    return self.defer(label, Message::SubmitEditor, None, editor);
+                };
+                let Some(editor) = &mut self.editor else {
+                    return Task::none();
+                };
+                match editor.field {
+                    EditorField::Name => {
+                        let new_name = editor.value.trim().to_string();
+                        if let Err(e) = validate_name(&new_name, &editor.path, &self.environments) {
+                            editor.error = Some(e.message());
+                            return Task::none();
+                        }
+                        editor.in_progress = true;
+                        let path = editor.path.clone();
+                        let why = fl!(
+                            "inhibit-rename",
+                            name = self.environment_name(&path),
+                            new_name = new_name.clone()
+                        );
+                        return Task::perform(
+                            async move {
+                                let _lock = inhibit_shutdown(&*backend, why).await;
+                                let result = backend.rename(&path, &new_name).await;
+                                (path, new_name, result)
+                            },
+                            |(path, new_name, result)| {
+                                cosmic::Action::App(Message::Renamed(path, new_name, result))
+                            },
+                        );
+                    }
+                    EditorField::Description => {
+                        let description = editor.value.trim().to_string();
+                        if description.chars().count() > MAX_DESCRIPTION_LEN {
+                            editor.error =
+                                Some(fl!("description-too-long", max = MAX_DESCRIPTION_LEN));
+                            return Task::none();
+                        }
+                        editor.in_progress = true;
+                        let path = editor.path.clone();
+                        return Task::perform(
+                            async move {
+                                let result = backend.describe(&path, &description).await;
+                                (path, result)
+                            },
+                            |(path, result)| cosmic::Action::App(Message::Described(path, result)),
+                        );
+                    }
+                }
+            }
+            Message::Renamed(path, new_name, result) => match result {
+                Ok(()) => {
+                    tracing::info!(
+                        path = path.to_string(),
+                        new_name,
+                        "Renamed boot environment"
+                    );
+                    if self
+                        .editor
+                        .as_ref()
+                        .is_some_and(|editor| editor.field == EditorField::Name)
+                    {
+                        self.editor = None;
+                    }
+                }
+                Err(e) => {
+                    tracing::error!(path = path.to_string(), new_name, error = ?e, "Failed to rename boot environment");
+                    if let Some(editor) = &mut self.editor {
+                        editor.in_progress = false;
+                        editor.error = Some(e.message());
+                    }
+                }
+            },
+            Message::Described(path, result) => match result {
+                Ok(()) => {
+                    tracing::info!(
+                        path = path.to_string(),
+                        "Updated boot environment description"
+                    );
+                    if self
+                        .editor
+                        .as_ref()
+                        .is_some_and(|editor| editor.field == EditorField::Description)
+                    {
+                        self.editor = None;
+                    }
+                }
+                Err(e) => {
+                    tracing::error!(path = path.to_string(), error = ?e, "Failed to update boot environment description");
+                    if let Some(editor) = &mut self.editor {
+                        editor.in_progress = false;
+                        editor.error = Some(e.message());
+                    }
+                }
+            },
+            Message::MountReadOnly(value) => {
+                self.mount_read_only = value;
+            }
+            Message::Mount(path) => {
+                let Some(env) = self
+                    .environments
+                    .iter()
+                    .find(|env| env.path == path && env.name_known())
+                else {
+                    return Task::none();
+                };
+                let Some(backend) = self.ready_backend() else {
+                    let label = fl!("pending-mount", name = env.name.clone());
+                    return self.defer(label, Message::Mount(path), None, None);
+                };
+                let mountpoint = match mount_root() {
+                    Ok(root) => root.join(&env.name),
+                    Err(e) => {
+                        return Task::done(cosmic::Action::App(Message::Mounted(path, Err(e))))
+                    }
+                };
+                let read_only = self.mount_read_only;
+                self.mount_in_progress = Some(path.clone());
+                return Task::perform(
+                    async move {
+                        let result =
+                            mount_boot_environment(&*backend, &path, mountpoint, read_only).await;
+                        (path, result)
+                    },
+                    |(path, result)| cosmic::Action::App(Message::Mounted(path, result)),
+                );
+            }
+            Message::Mounted(path, result) => {
+                self.mount_in_progress = None;
+                match result {
+                    Ok(mountpoint) => {
+                        tracing::info!(
+                            path = path.to_string(),
+                            mountpoint,
+                            "Mounted boot environment"
+                        );
+                        return self.update(Message::OpenMountpoint(mountpoint));
+                    }
+                    Err(e) => {
+                        tracing::error!(path = path.to_string(), error = ?e, "Failed to mount boot environment");
+                        let name = self.environment_name(&path);
+                        self.show_error(
+                            fl!("error-mount", name = name),
+                            e.into(),
+                            Some(Message::Mount(path)),
+                        );
+                    }
+                }
+            }
+            Message::OpenMountpoint(mountpoint) => {
+                if let Err(e) = open::that_detached(&mountpoint) {
+                    tracing::error!(mountpoint, error = ?e, "Failed to open mountpoint");
+                    self.show_error(
+                        fl!("error-open", mountpoint = mountpoint.clone()),
+                        AppError::Other(e.to_string()),
+                        Some(Message::OpenMountpoint(mountpoint)),
+                    );
+                }
+            }
+            Message::Unmount(path, force) => {
+                let Some(backend) = self.ready_backend() else {
+                    let label = fl!("pending-unmount", name = self.environment_name(&path));
+                    return self.defer(label, Message::Unmount(path, force), None, None);
+                };
+                self.mount_in_progress = Some(path.clone());
+                self.unmount_failed = None;
+                return Task::perform(
+                    async move {
+                        let result = backend.unmount(&path, force).await;
+                        (path, result)
+                    },
+                    |(path, result)| cosmic::Action::App(Message::Unmounted(path, result)),
+                );
+            }
+            Message::Unmounted(path, result) => {
+                self.mount_in_progress = None;
+                match result {
+                    Ok(mountpoint) => {
+                        tracing::info!(
+                            path = path.to_string(),
+                            mountpoint,
+                            "Unmounted boot environment"
+                        );
+                    }
+                    Err(e) => {
+                        tracing::error!(path = path.to_string(), error = ?e, "Failed to unmount boot environment");
+                        let name = self.environment_name(&path);
+                        self.show_error(fl!("error-unmount", name = name), e.into(), None);
+                        // Most likely something is still using it; offer to
+                        // force the issue.
+                        self.unmount_failed = Some(path);
+                    }
+                }
+            }
+            Message::ToggleExpanded(path) => {
+                if self.expanded.as_ref() == Some(&path) {
+                    self.set_expanded(None);
+                } else {
+                    self.set_expanded(Some(path));
+                }
+            }
+            Message::Added(env) => {
+                tracing::info!(path = ?env.path, name = %env.name, "Boot environment added");
+                let task = self.load_snapshots_task(env.path.clone());
+
+                // A renamed boot environment may come back under a new path;
+                // keep it open if it was before.
+                if let Some(editor) = &self.editor {
+                    if editor.in_progress
+                        && editor.field == EditorField::Name
+                        && editor.value.trim() == env.name
+                    {
+                        if self.expanded.as_ref() == Some(&editor.path) {
+                            self.expanded = Some(env.path.clone());
+                        }
+                        self.editor = None;
+                    }
+                }
+
+                if let Some(existing) = self.environments.iter_mut().find(|e| e.path == env.path) {
+                    *existing = env;
+                } else {
+                    // Keep the list in creation order, which also puts a
+                    // re-added environment back where it was.
+                    let idx = self
+                        .environments
+                        .partition_point(|e| e.created <= env.created);
+                    self.environments.insert(idx, env);
+                }
+                self.remember_boot_once();
+                return Task::batch([task, self.notify_later()]);
+            }
+            Message::Removed(path) => {
+                tracing::info!(?path, "Boot environment removed");
+                self.environments.retain(|env| env.path != path);
+                self.snapshots.remove(&path);
+
+                // Don't collapse an environment that is being renamed, in case
+                // it is about to be re-added under a new path.
+                let renaming = self.editor.as_ref().is_some_and(|editor| {
+                    editor.path == path && editor.field == EditorField::Name && editor.in_progress
+                });
+                if self.expanded.as_ref() == Some(&path) && !renaming {
+                    self.set_expanded(None);
+                }
+                return self.notify_later();
+            }
+            Message::PropertiesChanged(path, changes) => {
+                let Some(env) = self.environments.iter_mut().find(|env| env.path == path) else {
+                    // Most likely we haven't seen InterfacesAdded for it yet.
+                    tracing::debug!(
+                        path = path.to_string(),
+                        "Properties changed for an unknown boot environment"
+                    );
+                    return self.update(Message::BootEnvironmentsModified);
+                };
+                // Snapshots are named after the boot environment and count
+                // towards its space, so refresh them if either changes.
+                let snapshots_changed = changes.name.is_some() || changes.space.is_some();
+                let boot_changed = changes.next_boot.is_some() || changes.boot_once.is_some();
+                env.apply(changes);
+                let notify = if boot_changed {
+                    self.remember_boot_once();
+                    self.notify_later()
+                } else {
+                    Task::none()
+                };
+                if snapshots_changed {
+                    return Task::batch([notify, self.load_snapshots_task(path)]);
+                }
+                return notify;
+            }
+            Message::BootEnvironmentsModified => {
+                if !self.reload_pending {
+                    self.reload_pending = true;
+                    return Task::perform(tokio::time::sleep(RELOAD_DELAY), |()| {
+                        cosmic::Action::App(Message::ReloadBootEnvironments)
+                    });
+                }
+            }
+            Message::ReloadBootEnvironments => {
+                self.reload_pending = false;
+                return self.load_environments_task();
+            }
+            Message::KeepTrial => {
+                if let Some(path) = self.trial_environment().map(|env| env.path.clone()) {
+                    return self.update(Message::ActivateEnvironment(path, false));
+                }
+            }
+            Message::EndTrial => {
+                // The default is still the environment booted before, so
+                // there is nothing to change.
+                self.trial = None;
+                self.change_state(|state| state.boot_once = None);
+            }
+            Message::ActivateEnvironment(path, temporary) => {
+                if !temporary && self.trial.take().is_some() {
+                    // Choosing a new default settles whether to keep the
+                    // environment being tried out.
+                    self.change_state(|state| state.boot_once = None);
+                }
+                if let Some(backend) = self.ready_backend() {
+                    self.requested_target = Some((path.clone(), temporary));
+                    return Task::perform(
+                        async move {
+                            let result = backend.activate(&path, temporary).await;
+                            (path, result)
+                        },
+                        move |(path, result)| {
+                            cosmic::Action::App(Message::Activated(path, temporary, result))
+                        },
+                    );
+                }
+                let name = self.environment_name(&path);
+                let label = if temporary {
+                    fl!("pending-activate-once", name = name)
+                } else {
+                    fl!("pending-activate", name = name)
+                };
+                return self.defer(
+                    label,
+                    Message::ActivateEnvironment(path, temporary),
+                    None,
+                    None,
+                );
+            }
+            Message::Activated(path, temporary, result) => match result {
+                Ok(()) if temporary => tracing::info!(
+                    path = path.to_string(),
+                    "Temporarily activated boot environment"
+                ),
+                Ok(()) => tracing::info!(
+                    path = path.to_string(),
+                    "Permanently activated boot environment"
+                ),
+                Err(e) => {
+                    tracing::error!(path = path.to_string(), error = ?e, "Failed to activate boot environment");
+                    if self.requested_target.as_ref() == Some(&(path.clone(), temporary)) {
+                        self.requested_target = None;
+                    }
+                    let name = self.environment_name(&path);
+                    self.show_error(
+                        fl!("error-activate", name = name),
+                        e,
+                        Some(Message::ActivateEnvironment(path, temporary)),
+                    );
+                }
+            },
+            Message::TogglePopup => {
+                return if let Some(p) = self.popup.take() {
+                    // Don't reboot behind the user's back.
+                    self.reboot = None;
+                    self.settings_open = false;
+                    destroy_popup(p)
+                } else {
+                    let new_id = Id::unique();
+                    self.popup.replace(new_id);
+                    // Suggest a fresh name for the next snapshot.
+                    if !self.snapshot_in_progress {
+                        self.snapshot_name = default_snapshot_name();
+                        self.last_snapshot = None;
+                    }
+                    let popup_settings = self.core.applet.get_popup_settings(
+                        self.core.main_window_id().unwrap(),
+                        new_id,
+                        None,
+                        None,
+                        None,
+                    );
+                    // Policy may have changed since the last time the popup
+                    // was open.
+                    Task::batch([get_popup(popup_settings), self.can_reboot_task()])
+                };
+            }
+            Message::PopupClosed(id) => {
+                if self.popup.as_ref() == Some(&id) {
+                    self.popup = None;
+                    self.reboot = None;
+                    self.settings_open = false;
+                }
+            }
+        }
+        Task::none()
+    }
 
     fn style(&self) -> Option<cosmic::iced_runtime::Appearance> {
         Some(cosmic::applet::style())
     }
 }
 
-/// A stream of Added and Removed messages for the underlying boot environments.
-fn object_manager_stream(
-    conn: zbus::Connection,
-) -> impl cosmic::iced::futures::Stream<Item = Message> {
-    cosmic::iced::stream::channel(32, move |mut channel| async move {
-        let object_manager = match ObjectManagerProxy::builder(&conn)
-            .destination("ca.kamacite.BootEnvironments1")
-            // SAFETY: Safe to unwrap because the destination and path are known to be valid.
-            .unwrap()
-            .path("/ca/kamacite/BootEnvironments")
-            .unwrap()
-            .build()
-            .await
-        {
-            Ok(proxy) => proxy,
-            Err(e) => {
-                tracing::error!(error = ?e, "Failed to create ObjectManager proxy, updates will be ignored");
-                return;
+impl AppModel {
+    /// Labels for the activation dropdowns, one per boot environment.
+    fn dropdown_labels(&self) -> Vec<String> {
+        self.environments
+            .iter()
+            .map(BootEnvironmentObject::label)
+            .collect()
+    }
+
+    /// The index of the boot environment to boot into next time only, if
+    /// any.
+    fn boot_once_idx(&self) -> Option<usize> {
+        self.environments.iter().position(|e| e.boot_once)
+    }
+
+    /// The index of the default boot environment, if any.
+    fn next_boot_idx(&self) -> Option<usize> {
+        self.environments.iter().position(|e| e.next_boot)
+    }
+
+    /// The boot environment that rebooting will start, if it isn't the one
+    /// already running.
+    fn reboot_target(&self) -> Option<&BootEnvironmentObject> {
+        self.environments
+            .iter()
+            .find(|e| e.boot_once)
+            .or_else(|| self.environments.iter().find(|e| e.next_boot))
+            .filter(|e| !e.active)
+    }
+
+    /// The boot environment being tried out, if the user hasn't yet decided
+    /// whether to keep it.
+    fn trial_environment(&self) -> Option<&BootEnvironmentObject> {
+        let path = self.trial.as_ref()?;
+        self.environments
+            .iter()
+            .find(|env| &env.path == path && env.is_temporarily_booted())
+    }
+
+    /// The label of the default boot environment, if any.
+    fn default_label(&self) -> String {
+        self.environments
+            .iter()
+            .find(|env| env.next_boot)
+            .map(BootEnvironmentObject::label)
+            .unwrap_or_default()
+    }
+
+    /// The total space used by all boot environments, in bytes.
+    fn total_space(&self) -> u64 {
+        self.environments
+            .iter()
+            .fold(0u64, |total, env| total.saturating_add(env.space))
+    }
+
+    /// The backend, if the service is reachable through it.
+    fn ready_backend(&self) -> Option<Arc<dyn Backend>> {
+        self.backend.clone().filter(|_| self.service_available)
+    }
+
+    /// Hold an action until the service is reachable, replacing any earlier
+    /// pending action with the same label, and give up on it after
+    /// [`PENDING_TIMEOUT`].
+    fn defer(
+        &mut self,
+        label: String,
+        message: Message,
+        confirmation: Option<Confirmation>,
+        editor: Option<Editor>,
+    ) -> Task<cosmic::Action<Message>> {
+        tracing::info!(
+            action = label,
+            "Waiting for the service to perform an action"
+        );
+        let id = self.next_pending_id;
+        self.next_pending_id = self.next_pending_id.wrapping_add(1);
+        self.pending.retain(|action| action.label != label);
+        self.pending.push(PendingAction {
+            id,
+            label,
+            message,
+            confirmation,
+            editor,
+        });
+        Task::perform(tokio::time::sleep(PENDING_TIMEOUT), move |()| {
+            cosmic::Action::App(Message::PendingExpired(id))
+        })
+    }
+
+    /// What confirming a destructive action will do, for the list of pending
+    /// actions.
+    fn confirmation_label(&self, confirmation: &Confirmation) -> String {
+        match confirmation {
+            Confirmation::Rollback { path, snapshot, .. } => fl!(
+                "pending-rollback",
+                name = self.environment_name(path),
+                snapshot = snapshot.clone()
+            ),
+            Confirmation::DestroySnapshots { snapshots, .. } => {
+                fl!("pending-destroy-snapshots", count = snapshots.len())
             }
-        };
+            Confirmation::DestroyEnvironment { name, .. } => {
+                fl!("pending-destroy-environment", name = name.clone())
+            }
+        }
+    }
 
-        let mut added_stream = match object_manager.receive_interfaces_added().await {
-            Ok(stream) => stream,
-            Err(e) => {
-                tracing::error!(error = ?e, "Failed to start streaming InterfacesAdded signal");
-                return;
+    /// Why shutting down is blocked while a confirmed action is carried out.
+    fn inhibit_reason(&self, confirmation: &Confirmation) -> String {
+        match confirmation {
+            Confirmation::Rollback { path, snapshot, .. } => fl!(
+                "inhibit-rollback",
+                name = self.environment_name(path),
+                snapshot = snapshot.clone()
+            ),
+            Confirmation::DestroySnapshots {
+                path, snapshots, ..
+            } => fl!(
+                "inhibit-destroy-snapshots",
+                name = self.environment_name(path),
+                count = snapshots.len()
+            ),
+            Confirmation::DestroyEnvironment { name, .. } => {
+                fl!("inhibit-destroy-environment", name = name.clone())
             }
+        }
+    }
+
+    /// Remember the boot environment scheduled to boot once, whoever
+    /// scheduled it, so that it can be offered to be kept after booting into
+    /// it.
+    fn remember_boot_once(&mut self) {
+        let Some(path) = self
+            .environments
+            .iter()
+            .find(|env| env.boot_once)
+            .map(|env| env.path.to_string())
+        else {
+            return;
         };
+        self.change_state(|state| state.boot_once = Some(path));
+    }
 
-        let mut removed_stream = match object_manager.receive_interfaces_removed().await {
-            Ok(stream) => stream,
-            Err(e) => {
-                tracing::error!(error = ?e, "Failed to start streaming InterfacesRemoved signal");
-                return;
+    /// Change what the applet remembers, saving it for next time.
+    fn change_state(&mut self, change: impl FnOnce(&mut State)) {
+        let previous = self.state.clone();
+        change(&mut self.state);
+        if self.state == previous {
+            return;
+        }
+        if let Some(handler) = &self.state_handler {
+            if let Err(e) = self.state.write_entry(handler) {
+                tracing::error!(error = ?e, "Failed to save state");
             }
+        }
+    }
+
+    /// Change the settings, saving them for next time.
+    fn change_config(&mut self, change: impl FnOnce(&mut Config)) {
+        change(&mut self.config);
+        if let Some(handler) = &self.config_handler {
+            if let Err(e) = self.config.write_entry(handler) {
+                tracing::error!(error = ?e, "Failed to save settings");
+            }
+        }
+    }
+
+    /// Schedule a notification about recent changes, unless one already is.
+    fn notify_later(&mut self) -> Task<cosmic::Action<Message>> {
+        if self.notify_pending || self.notifier.is_none() {
+            return Task::none();
+        }
+        self.notify_pending = true;
+        Task::perform(tokio::time::sleep(NOTIFY_DELAY), |()| {
+            cosmic::Action::App(Message::NotifyChanges)
+        })
+    }
+
+    /// Spawn a task to tell the user what changed since `previous`, as far
+    /// as the settings allow.
+    fn notify_changes_task(
+        &self,
+        previous: &BootTargets,
+        current: &BootTargets,
+    ) -> Task<cosmic::Action<Message>> {
+        let Some(notifier) = self.notifier.clone() else {
+            return Task::none();
         };
 
-        loop {
-            tokio::select! {
-                Some(signal) = added_stream.next() => {
-                    if let Ok(args) = signal.args() {
-                        if let Some(props) = args.interfaces_and_properties.get("ca.kamacite.BootEnvironment") {
-                            let path = From::from(args.object_path);
-                            match BootEnvironmentObject::from_properties(path, props) {
-                                Ok(env) => {
-                                    // TODO: Should we log errors here?
-                                    let _ = channel.send(Message::Added(env)).await;
-                                }
-                                Err(e) => {
-                                    tracing::error!(error = ?e, "Failed to parse boot environment object");
-                                }
-                            }
-                        }
-                    }
+        let mut lines = Vec::new();
+        let mut undo = Vec::new();
+        let mut retargeted = false;
+        if self.config.notify_boot_changes {
+            if current.next_boot != previous.next_boot {
+                retargeted = true;
+                if let Some(path) = &current.next_boot {
+                    lines.push(fl!("notify-default", name = current.label(path)));
                 }
-                Some(signal) = removed_stream.next() => {
-                    if let Ok(args) = signal.args() {
-                        let path = From::from(args.object_path);
-                        // TODO: Should we log errors here?
-                        let _ = channel.send(Message::Removed(path)).await;
-                    }
+                if let Some(path) = previous
+                    .next_boot
+                    .as_ref()
+                    .filter(|path| current.labels.contains_key(*path))
+                {
+                    undo.push(Message::ActivateEnvironment(path.clone(), false));
+                }
+            }
+            if current.boot_once != previous.boot_once {
+                retargeted = true;
+                match &current.boot_once {
+                    Some(path) => lines.push(fl!("notify-boot-once", name = current.label(path))),
+                    None => lines.push(fl!("notify-boot-once-cleared")),
+                }
+                if let Some(path) = previous
+                    .boot_once
+                    .as_ref()
+                    .filter(|path| current.labels.contains_key(*path))
+                {
+                    undo.push(Message::ActivateEnvironment(path.clone(), true));
                 }
             }
         }
-    })
-}
-
-/// A stream of `PropertiesChanged` messages for all boot environments.
-fn properties_changed_stream(
-    conn: zbus::Connection,
-) -> impl cosmic::iced::futures::Stream<Item = Message> {
-    cosmic::iced::stream::channel(32, move |mut channel| async move {
-        // Match against all PropertiesChanged signals in the boot environment
-        // namespace.
-        let rule = match zbus::MatchRule::builder()
-            .msg_type(zbus::message::Type::Signal)
-            .interface("org.freedesktop.DBus.Properties")
-            .and_then(|b| b.member("PropertiesChanged"))
-            .and_then(|b| b.path_namespace("/ca/kamacite/BootEnvironments"))
-        {
-            Ok(builder) => builder.build(),
-            Err(e) => {
-                tracing::error!(error = ?e, "Failed to build match rule for PropertiesChanged");
-                return;
+        if self.config.notify_environment_changes {
+            for label in current.labels_missing_from(previous) {
+                lines.push(fl!("notify-added", name = label));
+            }
+            for label in previous.labels_missing_from(current) {
+                lines.push(fl!("notify-removed", name = label));
             }
+        }
+        if lines.is_empty() {
+            return Task::none();
+        }
+
+        let mut actions = Vec::new();
+        // Undoing several changes could fail halfway, so only offer to undo
+        // one.
+        if let [undo] = &undo[..] {
+            actions.push(("undo", fl!("undo"), undo.clone()));
+        }
+        if retargeted && self.can_reboot && self.reboot_target().is_some() {
+            actions.push(("reboot", fl!("reboot-now"), Message::ShowRebootCountdown));
+        }
+
+        // Replace the last notification about changes rather than piling
+        // them up.
+        let replaces = self
+            .notifications
+            .iter()
+            .rev()
+            .find(|sent| sent.changes)
+            .map(|sent| sent.id);
+        notify_task(
+            notifier,
+            fl!("notify-summary"),
+            lines.join("\n"),
+            actions,
+            replaces,
+            true,
+        )
+    }
+
+    /// Spawn a task to ask, once, whether to keep the boot environment being
+    /// tried out.
+    fn notify_trial_task(&mut self) -> Task<cosmic::Action<Message>> {
+        if self.trial_notified || !self.config.notify_boot_changes {
+            return Task::none();
+        }
+        let Some(notifier) = self.notifier.clone() else {
+            return Task::none();
+        };
+        let Some(summary) = self
+            .trial_environment()
+            .map(|trial| fl!("trial-title", name = trial.label()))
+        else {
+            return Task::none();
         };
+        self.trial_notified = true;
+        notify_task(
+            notifier,
+            summary,
+            fl!("trial-details", default = self.default_label()),
+            vec![
+                ("keep", fl!("trial-keep"), Message::KeepTrial),
+                ("go-back", fl!("trial-go-back"), Message::EndTrial),
+            ],
+            None,
+            false,
+        )
+    }
 
-        let mut stream = match zbus::MessageStream::for_match_rule(rule, &conn, Some(32)).await {
-            Ok(stream) => stream,
-            Err(e) => {
-                tracing::error!(error = ?e, "Failed to start streaming PropertiesChanged signals");
-                return;
+    /// Show an error banner, replacing any earlier banner with the same summary.
+    fn show_error(&mut self, summary: String, error: AppError, retry: Option<Message>) {
+        self.banners.retain(|banner| banner.summary != summary);
+        if self.banners.len() >= MAX_BANNERS {
+            self.banners.remove(0);
+        }
+        self.banners.push(Banner {
+            id: self.next_banner_id,
+            summary,
+            error,
+            retry,
+        });
+        self.next_banner_id = self.next_banner_id.wrapping_add(1);
+    }
+
+    /// The name of a boot environment for use in messages, falling back to
+    /// its object path if it has gone away.
+    fn environment_name(&self, path: &zvariant::OwnedObjectPath) -> String {
+        self.environments
+            .iter()
+            .find(|env| &env.path == path)
+            .map_or_else(|| path.to_string(), |env| env.name.clone())
+    }
+
+    /// Describes the error banners, most recent last, followed by any pending
+    /// actions.
+    fn view_banners(&self) -> Element<'_, Message> {
+        let Spacing {
+            space_xxs, space_s, ..
+        } = theme::active().cosmic().spacing;
+
+        let mut banners = column![].spacing(space_xxs);
+        for banner in &self.banners {
+            let mut actions = row![].spacing(space_xxs).align_y(Alignment::Center);
+            if banner.retry.is_some() {
+                actions = actions
+                    .push(button::text(fl!("retry")).on_press(Message::RetryBanner(banner.id)));
             }
+            actions = actions.push(
+                button::icon(cosmic::widget::icon::from_name("window-close-symbolic"))
+                    .on_press(Message::DismissBanner(banner.id)),
+            );
+
+            banners = banners.push(padded_control(
+                row![
+                    cosmic::widget::icon::from_name("dialog-error-symbolic").size(24),
+                    column![
+                        text::body(banner.summary.as_str()),
+                        text::caption(banner.error.message()),
+                    ]
+                    .width(Length::Fill),
+                    actions,
+                ]
+                .align_y(Alignment::Center)
+                .spacing(space_s),
+            ));
+        }
+
+        for action in &self.pending {
+            banners = banners.push(padded_control(
+                row![
+                    cosmic::widget::icon::from_name("emblem-synchronizing-symbolic").size(24),
+                    column![
+                        text::body(action.label.as_str()),
+                        text::caption(fl!("pending")),
+                    ]
+                    .width(Length::Fill),
+                    button::icon(cosmic::widget::icon::from_name("window-close-symbolic"))
+                        .on_press(Message::CancelPending(action.id)),
+                ]
+                .align_y(Alignment::Center)
+                .spacing(space_s),
+            ));
+        }
+
+        banners.into()
+    }
+
+    /// Spawn a task to (re)load the list of boot environments.
+    fn load_environments_task(&self) -> Task<cosmic::Action<Message>> {
+        let Some(backend) = self.backend.clone() else {
+            return Task::none();
         };
+        Task::perform(
+            async move {
+                let mut environments = backend.list().await?;
+                // Sort by creation time.
+                environments.sort_by_key(|env| env.created);
+                Ok(environments)
+            },
+            |result| match result {
+                Ok(environments) => {
+                    cosmic::Action::App(Message::BootEnvironmentsLoaded(environments))
+                }
+                Err(AppError::ServiceUnknown) => {
+                    tracing::warn!("Boot environment service is not running");
+                    cosmic::Action::App(Message::ServiceOwnerChanged(false))
+                }
+                Err(e) => {
+                    tracing::error!(error = ?e, "Failed to load boot environments");
+                    error_action(fl!("error-load"), e, Some(Message::ReloadBootEnvironments))
+                }
+            },
+        )
+    }
 
-        while let Some(msg_result) = stream.next().await {
-            match msg_result {
-                Ok(msg) => {
-                    // We treat all property changes as triggering a reload.
-                    // This isn't terribly efficient, but it does sidestep our
-                    // getting out of sync with the backend by being too clever
-                    // with our caching.
-                    //
-                    // Unfortunately, it also means that when multiple
-                    // properties change -- which is common when a boot
-                    // environment is activated -- we reload multiple times in
-                    // succession.
-                    let _ = channel.send(Message::BootEnvironmentsModified).await;
-
-                    // We only need to parse the message for debug logs, so make
-                    // this whole step conditional.
-                    if tracing::enabled!(tracing::Level::DEBUG) {
-                        match msg
-                            .body()
-                            .deserialize::<(String, HashMap<String, zvariant::Value<'_>>, Vec<String>)>()
-                        {
-                            Ok((iface, changed, _)) => {
-                                let props: Vec<&str> = changed.keys().map(std::string::String::as_str).collect();
-                                tracing::debug!(
-                                    path = msg
-                                        .header()
-                                        .path()
-                                        .map(std::string::ToString::to_string)
-                                        .unwrap_or_default(),
-                                    iface,
-                                    props = props.join(","),
-                                    "One or more BootEnvironment properties updated"
-                                );
-                            }
-                            Err(e) => {
-                                tracing::error!(error = ?e, "Failed to parse PropertiesChanged signal");
-                            }
-                        }
-                    }
+    /// Spawn a task to find out whether the user may reboot.
+    fn can_reboot_task(&self) -> Task<cosmic::Action<Message>> {
+        let Some(backend) = self.backend.clone() else {
+            return Task::none();
+        };
+        Task::perform(async move { backend.can_reboot().await }, |result| {
+            let can_reboot = result.unwrap_or_else(|e| {
+                // Not worth a banner: the user can still reboot some other
+                // way.
+                tracing::warn!(error = ?e, "Failed to check whether rebooting is allowed");
+                false
+            });
+            cosmic::Action::App(Message::CanReboot(can_reboot))
+        })
+    }
+
+    /// Spawn a task to list the programs blocking a reboot.
+    fn load_inhibitors_task(&self, id: u64) -> Task<cosmic::Action<Message>> {
+        let Some(backend) = self.backend.clone() else {
+            return Task::none();
+        };
+        Task::perform(
+            async move { backend.shutdown_inhibitors().await },
+            move |result| match result {
+                Ok(inhibitors) => cosmic::Action::App(Message::InhibitorsLoaded(id, inhibitors)),
+                Err(e) => {
+                    tracing::warn!(error = ?e, "Failed to list shutdown inhibitors");
+                    cosmic::Action::None
                 }
+            },
+        )
+    }
+
+    /// Forget everything learned from the service, which is stale once it
+    /// or the connection to it has gone away.
+    fn forget_environments(&mut self) {
+        self.environments.clear();
+        self.snapshots.clear();
+        self.set_expanded(None);
+        self.confirmation = None;
+        self.editor = None;
+        self.reboot = None;
+        self.mount_in_progress = None;
+        self.unmount_failed = None;
+        self.snapshot_in_progress = false;
+    }
+
+    /// Change which boot environment's snapshots are shown, forgetting any
+    /// per-environment selection state.
+    fn set_expanded(&mut self, path: Option<zvariant::OwnedObjectPath>) {
+        if self.expanded != path {
+            self.selected_snapshots.clear();
+            self.snapshot_failures.clear();
+        }
+        self.expanded = path;
+    }
+
+    /// Spawn a task to (re)load the snapshots of a boot environment.
+    fn load_snapshots_task(
+        &self,
+        path: zvariant::OwnedObjectPath,
+    ) -> Task<cosmic::Action<Message>> {
+        let Some(backend) = self.backend.clone() else {
+            return Task::none();
+        };
+        Task::perform(
+            async move {
+                let result = backend.snapshots(&path).await.map(|mut snapshots| {
+                    // Sort by creation time.
+                    snapshots.sort_by_key(|s| s.created);
+                    snapshots
+                });
+                (path, result)
+            },
+            |(path, result)| match result {
+                Ok(snapshots) => cosmic::Action::App(Message::SnapshotsLoaded(path, snapshots)),
                 Err(e) => {
-                    tracing::error!(error = ?e, "Error receiving PropertiesChanged signal");
+                    tracing::error!(path = path.to_string(), error = ?e, "Failed to load snapshots");
+                    error_action(fl!("error-load-snapshots"), e, None)
                 }
+            },
+        )
+    }
+
+    /// Describes the choice between keeping the boot environment being tried
+    /// out and going back to the default.
+    fn view_trial(&self, trial: &BootEnvironmentObject) -> Element<'_, Message> {
+        let Spacing {
+            space_xxs, space_s, ..
+        } = theme::active().cosmic().spacing;
+
+        padded_control(
+            column![
+                row![
+                    cosmic::widget::icon::from_name("dialog-information-symbolic").size(24),
+                    column![
+                        text::body(fl!("trial-title", name = trial.label())),
+                        text::caption(fl!("trial-details", default = self.default_label())),
+                    ]
+                    .width(Length::Fill),
+                ]
+                .align_y(Alignment::Center)
+                .spacing(space_s),
+                row![
+                    cosmic::widget::horizontal_space(),
+                    button::standard(fl!("trial-go-back")).on_press(Message::EndTrial),
+                    button::suggested(fl!("trial-keep")).on_press(Message::KeepTrial),
+                ]
+                .spacing(space_s),
+            ]
+            .spacing(space_xxs),
+        )
+        .into()
+    }
+
+    /// Describes the controls for taking a snapshot of the active boot environment.
+    fn view_create_snapshot(&self, active_env: &BootEnvironmentObject) -> Element<'_, Message> {
+        let Spacing { space_xxs, .. } = theme::active().cosmic().spacing;
+
+        let can_create = !self.snapshot_in_progress && !self.snapshot_name.trim().is_empty();
+
+        let mut controls = column![
+            text_input(fl!("snapshot-name"), &self.snapshot_name)
+                .on_input(Message::SnapshotNameInput),
+            text_input(fl!("snapshot-description"), &self.snapshot_description)
+                .on_input(Message::SnapshotDescriptionInput),
+            button::standard(fl!("snapshot-now"))
+                .on_press_maybe(can_create.then_some(Message::CreateSnapshot)),
+        ]
+        .spacing(space_xxs);
+
+        if self.snapshot_in_progress {
+            controls = controls.push(text::caption(fl!(
+                "snapshot-in-progress",
+                name = active_env.name.clone()
+            )));
+        } else if let Some(name) = &self.last_snapshot {
+            controls = controls.push(text::caption(fl!("snapshot-created", name = name.clone())));
+        }
+
+        padded_control(controls).into()
+    }
+
+    /// Describes an inline editor for a boot environment property.
+    fn view_editor(&self, editor: &Editor) -> Element<'_, Message> {
+        let Spacing {
+            space_xxs, space_s, ..
+        } = theme::active().cosmic().spacing;
+
+        let placeholder = match editor.field {
+            EditorField::Name => fl!("rename-placeholder"),
+            EditorField::Description => fl!("describe-placeholder"),
+        };
+
+        // Validate as the user types, rather than waiting for the service to
+        // reject the change.
+        let error = editor.error.clone().or_else(|| match editor.field {
+            EditorField::Name => {
+                validate_name(editor.value.trim(), &editor.path, &self.environments)
+                    .err()
+                    .map(|e| e.message())
             }
+            EditorField::Description => (editor.value.trim().chars().count() > MAX_DESCRIPTION_LEN)
+                .then(|| fl!("description-too-long", max = MAX_DESCRIPTION_LEN)),
+        });
+
+        let can_submit = !editor.in_progress && error.is_none();
+
+        let mut controls = column![row![
+            text_input(placeholder, editor.value.clone())
+                .on_input(Message::EditorInput)
+                .width(Length::Fill),
+            button::standard(fl!("cancel")).on_press(Message::CancelEditor),
+            button::suggested(fl!("save"))
+                .on_press_maybe(can_submit.then_some(Message::SubmitEditor)),
+        ]
+        .align_y(Alignment::Center)
+        .spacing(space_s)]
+        .spacing(space_xxs);
+
+        if editor.field == EditorField::Description {
+            // Preview the label exactly as it will appear in the dropdown.
+            let name = self
+                .environments
+                .iter()
+                .find(|env| env.path == editor.path)
+                .map(|env| env.name.as_str())
+                .unwrap_or_default();
+            let description = editor.value.trim();
+            let preview = environment_label(name, (!description.is_empty()).then_some(description));
+
+            controls = controls.push(
+                row![
+                    text::caption(fl!(
+                        "describe-preview",
+                        label = preview,
+                        count = description.chars().count(),
+                        max = MAX_DESCRIPTION_LEN
+                    ))
+                    .width(Length::Fill),
+                    button::text(fl!("clear")).on_press_maybe(
+                        (!editor.in_progress && !editor.value.is_empty())
+                            .then(|| Message::EditorInput(String::new()))
+                    ),
+                ]
+                .align_y(Alignment::Center),
+            );
         }
-    })
+
+        if let Some(error) = error {
+            controls = controls.push(text::caption(error));
+        }
+
+        padded_control(controls).into()
+    }
+
+    /// Describes the actions available for an expanded boot environment.
+    fn view_environment_actions(&self, env: &BootEnvironmentObject) -> Element<'_, Message> {
+        let Spacing { space_xxs, .. } = theme::active().cosmic().spacing;
+
+        let editing = self.editor.as_ref().is_some_and(|e| e.path == env.path);
+
+        let mut actions = column![row![
+            button::text(fl!("rename")).on_press_maybe(
+                (!editing && env.name_known()).then(|| Message::StartRename(env.path.clone()))
+            ),
+            button::text(fl!("describe"))
+                .on_press_maybe((!editing).then(|| Message::StartDescribe(env.path.clone()))),
+            button::destructive(fl!("destroy-environment")).on_press_maybe(
+                env.can_destroy()
+                    .then(|| Message::RequestDestroyEnvironment(env.path.clone()))
+            ),
+        ]
+        .spacing(space_xxs)]
+        .spacing(space_xxs);
+
+        if !env.name_known() {
+            actions = actions.push(text::caption(fl!("environment-name-unknown")));
+        } else if !env.boot_state_known() {
+            actions = actions.push(text::caption(fl!("destroy-environment-unknown")));
+        } else if !env.can_destroy() {
+            actions = actions.push(text::caption(fl!("destroy-environment-blocked")));
+        }
+
+        // The running environment is already mounted at the root.
+        if !env.may_be_active() {
+            let busy = self.mount_in_progress.is_some();
+            if let Some(mountpoint) = &env.mountpoint {
+                let force = self.unmount_failed.as_ref() == Some(&env.path);
+                actions = actions.push(
+                    row![
+                        text::caption(fl!("mounted-at", mountpoint = mountpoint.clone()))
+                            .width(Length::Fill),
+                        button::text(fl!("open"))
+                            .on_press(Message::OpenMountpoint(mountpoint.clone())),
+                        button::text(if force {
+                            fl!("force-unmount")
+                        } else {
+                            fl!("unmount")
+                        })
+                        .on_press_maybe((!busy).then(|| Message::Unmount(env.path.clone(), force))),
+                    ]
+                    .align_y(Alignment::Center)
+                    .spacing(space_xxs),
+                );
+            } else {
+                actions = actions.push(
+                    row![
+                        checkbox(fl!("mount-read-only"), self.mount_read_only)
+                            .on_toggle(Message::MountReadOnly)
+                            .width(Length::Fill),
+                        button::text(fl!("mount")).on_press_maybe(
+                            (!busy && env.name_known()).then(|| Message::Mount(env.path.clone()))
+                        ),
+                    ]
+                    .align_y(Alignment::Center)
+                    .spacing(space_xxs),
+                );
+            }
+        }
+
+        // Show whatever else the service told us, in case it's useful.
+        for (name, value) in &env.other_properties {
+            actions = actions.push(text::caption(fl!(
+                "other-property",
+                name = name.clone(),
+                value = value.clone()
+            )));
+        }
+
+        padded_control(actions).into()
+    }
+
+    /// Describes the prompt for a destructive action awaiting confirmation.
+    fn view_confirmation(&self, confirmation: &Confirmation) -> Element<'_, Message> {
+        let Spacing {
+            space_xxs, space_s, ..
+        } = theme::active().cosmic().spacing;
+
+        let mut content = column![].spacing(space_xxs);
+
+        match confirmation {
+            Confirmation::Rollback {
+                path,
+                snapshot,
+                destroyed,
+                maybe_destroyed,
+                active,
+                override_active,
+            } => {
+                let name = self
+                    .environments
+                    .iter()
+                    .find(|env| &env.path == path)
+                    .map(|env| env.name.clone())
+                    .unwrap_or_default();
+
+                content = content.push(text::heading(fl!(
+                    "rollback-title",
+                    name = name,
+                    snapshot = snapshot.clone()
+                )));
+
+                if destroyed.is_empty() && maybe_destroyed.is_empty() {
+                    content = content.push(text::body(fl!("rollback-no-loss")));
+                }
+                if !destroyed.is_empty() {
+                    content = content.push(text::body(fl!(
+                        "rollback-destroys",
+                        count = destroyed.len()
+                    )));
+                    for name in destroyed {
+                        content = content.push(text::monotext(name.clone()));
+                    }
+                }
+                if !maybe_destroyed.is_empty() {
+                    content = content.push(text::body(fl!(
+                        "rollback-may-destroy",
+                        count = maybe_destroyed.len()
+                    )));
+                    for name in maybe_destroyed {
+                        content = content.push(text::monotext(name.clone()));
+                    }
+                }
+
+                if *active {
+                    content = content.push(text::body(fl!("rollback-active-warning")));
+                    content = content.push(
+                        checkbox(fl!("rollback-active-override"), *override_active)
+                            .on_toggle(Message::OverrideActiveRollback),
+                    );
+                }
+            }
+            Confirmation::DestroySnapshots {
+                snapshots, space, ..
+            } => {
+                content = content.push(text::heading(fl!(
+                    "destroy-snapshots-title",
+                    count = snapshots.len()
+                )));
+                for name in snapshots {
+                    content = content.push(text::monotext(name.clone()));
+                }
+                content = content.push(text::body(fl!(
+                    "destroy-snapshots-space",
+                    space = format_size(*space)
+                )));
+            }
+            Confirmation::DestroyEnvironment {
+                name,
+                typed,
+                force_unmount,
+                snapshots,
+                ..
+            } => {
+                content = content.push(text::heading(fl!(
+                    "destroy-environment-title",
+                    name = name.clone()
+                )));
+                content = content.push(text::body(fl!(
+                    "destroy-environment-prompt",
+                    name = name.clone()
+                )));
+                content = content.push(
+                    // The name is only given in the prompt, so that it has to
+                    // be typed out rather than copied from the placeholder.
+                    text_input(fl!("destroy-environment-placeholder"), typed.clone())
+                        .on_input(Message::DestroyConfirmationInput),
+                );
+                content = content.push(
+                    checkbox(fl!("destroy-force-unmount"), *force_unmount)
+                        .on_toggle(Message::DestroyForceUnmount),
+                );
+                content = content.push(
+                    checkbox(fl!("destroy-with-snapshots"), *snapshots)
+                        .on_toggle(Message::DestroyWithSnapshots),
+                );
+            }
+        }
+
+        let confirm_label = match confirmation {
+            Confirmation::Rollback { .. } => fl!("rollback"),
+            Confirmation::DestroySnapshots { .. } => fl!("delete"),
+            Confirmation::DestroyEnvironment { .. } => fl!("destroy-environment"),
+        };
+
+        let refusal = confirmation.refusal(&self.environments);
+        if let Some(error) = &refusal {
+            content = content.push(text::caption(error.message()));
+        }
+        content = content.push(
+            row![
+                cosmic::widget::horizontal_space(),
+                button::standard(fl!("cancel")).on_press(Message::CancelAction),
+                button::destructive(confirm_label).on_press_maybe(
+                    (confirmation.can_confirm() && refusal.is_none())
+                        .then_some(Message::ConfirmAction)
+                ),
+            ]
+            .spacing(space_s),
+        );
+
+        padded_control(content).padding([8, space_s]).into()
+    }
+
+    /// Describes the applet's settings.
+    fn view_settings(&self) -> Element<'_, Message> {
+        let Spacing {
+            space_xxs, space_s, ..
+        } = theme::active().cosmic().spacing;
+
+        let content = column![
+            text::heading(fl!("settings")),
+            checkbox(fl!("notify-boot-changes"), self.config.notify_boot_changes)
+                .on_toggle(Message::SetNotifyBootChanges),
+            checkbox(
+                fl!("notify-environment-changes"),
+                self.config.notify_environment_changes
+            )
+            .on_toggle(Message::SetNotifyEnvironmentChanges),
+            row![
+                cosmic::widget::horizontal_space(),
+                button::standard(fl!("back")).on_press(Message::CloseSettings),
+            ],
+        ]
+        .spacing(space_xxs);
+
+        padded_control(content).padding([8, space_s]).into()
+    }
+
+    /// Describes the countdown to a reboot, along with the programs that may
+    /// stop it.
+    fn view_reboot(&self, countdown: &RebootCountdown) -> Element<'_, Message> {
+        let Spacing {
+            space_xxs, space_s, ..
+        } = theme::active().cosmic().spacing;
+
+        let mut content = column![
+            text::heading(fl!("reboot-title", name = countdown.label.clone())),
+            text::body(fl!("reboot-countdown", seconds = countdown.remaining)),
+        ]
+        .spacing(space_xxs);
+
+        if !countdown.inhibitors.is_empty() {
+            content = content.push(text::body(fl!(
+                "reboot-inhibited",
+                count = countdown.inhibitors.len()
+            )));
+            for inhibitor in &countdown.inhibitors {
+                content = content.push(text::caption(fl!(
+                    "inhibitor",
+                    who = inhibitor.who.clone(),
+                    why = inhibitor.why.clone()
+                )));
+            }
+        }
+
+        content = content.push(
+            row![
+                cosmic::widget::horizontal_space(),
+                button::standard(fl!("cancel")).on_press(Message::CancelReboot),
+                button::destructive(fl!("reboot-now")).on_press(Message::RebootNow),
+            ]
+            .spacing(space_s),
+        );
+
+        padded_control(content).padding([8, space_s]).into()
+    }
+
+    /// Describes the list of snapshots for an expanded boot environment.
+    fn view_snapshots(&self, env: &BootEnvironmentObject) -> Element<'_, Message> {
+        let Spacing { space_xxs, .. } = theme::active().cosmic().spacing;
+
+        let Some(snapshots) = self.snapshots.get(&env.path) else {
+            return padded_control(text::caption(fl!("loading-snapshots"))).into();
+        };
+
+        if snapshots.is_empty() {
+            return padded_control(text::caption(fl!("no-snapshots"))).into();
+        }
+
+        let mut list = column![].spacing(space_xxs);
+        for snapshot in snapshots {
+            let name = snapshot.name.clone();
+            let selected = self.selected_snapshots.contains(&snapshot.name);
+
+            let title = if let Some(desc) = &snapshot.description {
+                text::body(format!("{} ({})", desc, snapshot.name))
+            } else {
+                text::monotext(&snapshot.name)
+            };
+
+            list = list.push(
+                row![
+                    checkbox("", selected)
+                        .on_toggle(move |value| Message::SelectSnapshot(name.clone(), value)),
+                    column![
+                        title,
+                        text::caption(fl!(
+                            "snapshot-details",
+                            space = format_size(snapshot.space),
+                            created = format_timestamp(snapshot.created)
+                        )),
+                    ]
+                    .width(Length::Fill),
+                    button::text(fl!("rollback")).on_press(Message::RequestRollback(
+                        env.path.clone(),
+                        snapshot.name.clone()
+                    )),
+                    button::text(fl!("delete")).on_press(Message::RequestDestroySnapshots(vec![
+                        snapshot.name.clone()
+                    ])),
+                ]
+                .align_y(Alignment::Center),
+            );
+        }
+
+        for (name, reason) in &self.snapshot_failures {
+            list = list.push(text::caption(fl!(
+                "destroy-snapshot-failed",
+                name = name.clone(),
+                reason = reason.clone()
+            )));
+        }
+
+        if !self.selected_snapshots.is_empty() {
+            let mut selected: Vec<String> = self.selected_snapshots.iter().cloned().collect();
+            selected.sort();
+            list = list.push(
+                button::destructive(fl!(
+                    "delete-selected",
+                    count = self.selected_snapshots.len()
+                ))
+                .on_press(Message::RequestDestroySnapshots(selected)),
+            );
+        }
+
+        padded_control(list).into()
+    }
 }
+
+/// The message for an event reported by the backend.
+fn event_message(event: Event) -> Message {
+    match event {
+        Event::Added(env) => Message::Added(env),
+        Event::Removed(path) => Message::Removed(path),
+        Event::Changed(path, changes) => Message::PropertiesChanged(path, changes),
+        Event::Modified => Message::BootEnvironmentsModified,
+    }
+}
+
+#[cfg(test)]
+mod tests;
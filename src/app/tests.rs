@@ -0,0 +1,935 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Drives `AppModel::update` against an in-memory backend, without a
+//! compositor.
+
+use std::collections::VecDeque;
+
+use futures_util::stream::BoxStream;
+use futures_util::FutureExt;
+
+use super::*;
+
+/// An applet connected to an in-memory backend. Tasks returned by `update`
+/// are run to completion, and the messages they produce are handled in turn,
+/// as are changes reported by the backend.
+struct Harness {
+    app: AppModel,
+    backend: Arc<MemoryBackend>,
+    events: BoxStream<'static, Event>,
+}
+
+impl Harness {
+    /// Start the applet without connecting it to the backend yet.
+    fn new(backend: MemoryBackend) -> Self {
+        let (mut app, _connect) = AppModel::init(cosmic::Core::default(), ());
+        // Leave the user's settings alone.
+        app.config_handler = None;
+        app.config = Config::default();
+        app.state_handler = None;
+        app.state = State::default();
+        let backend = Arc::new(backend);
+        Harness {
+            app,
+            events: backend.watch(),
+            backend,
+        }
+    }
+
+    /// Start the applet and connect it to a backend.
+    async fn connected(backend: MemoryBackend) -> Self {
+        let mut harness = Harness::new(backend);
+        harness.connect().await;
+        harness
+    }
+
+    async fn connect(&mut self) -> Vec<Message> {
+        self.send(Message::Connected(self.backend.clone())).await
+    }
+
+    /// Start sending notifications, which are kept in memory.
+    async fn notifier(&mut self) -> Arc<MemoryNotifier> {
+        let notifier = Arc::new(MemoryNotifier::default());
+        self.send(Message::NotifierReady(notifier.clone())).await;
+        notifier
+    }
+
+    /// Handle a message and everything that follows from it, returning the
+    /// messages produced by tasks along the way.
+    async fn send(&mut self, message: Message) -> Vec<Message> {
+        self.process(VecDeque::from([message])).await
+    }
+
+    /// Handle the changes the backend has reported so far.
+    async fn settle(&mut self) -> Vec<Message> {
+        self.process(VecDeque::new()).await
+    }
+
+    async fn process(&mut self, mut queue: VecDeque<Message>) -> Vec<Message> {
+        let mut produced = Vec::new();
+        loop {
+            // The subscription isn't running, so deliver backend events here.
+            while let Some(Some(event)) = self.events.next().now_or_never() {
+                queue.push_back(event_message(event));
+            }
+            let Some(message) = queue.pop_front() else {
+                return produced;
+            };
+            let task = self.app.update(message);
+            for message in run(task).await {
+                produced.push(message.clone());
+                queue.push_back(message);
+            }
+        }
+    }
+
+    fn names(&self) -> Vec<&str> {
+        self.app
+            .environments
+            .iter()
+            .map(|env| env.name.as_str())
+            .collect()
+    }
+
+    fn environment(&self, id: u32) -> &BootEnvironmentObject {
+        self.app
+            .environments
+            .iter()
+            .find(|env| env.path == path(id))
+            .unwrap()
+    }
+}
+
+/// Run a task to completion, collecting the application messages it produces.
+async fn run(task: Task<cosmic::Action<Message>>) -> Vec<Message> {
+    let Some(stream) = cosmic::iced_runtime::task::into_stream(task) else {
+        return Vec::new();
+    };
+    stream
+        .filter_map(|action| async move {
+            match action {
+                cosmic::iced_runtime::Action::Output(cosmic::Action::App(message)) => Some(message),
+                _ => None,
+            }
+        })
+        .collect()
+        .await
+}
+
+fn path(id: u32) -> zvariant::OwnedObjectPath {
+    zvariant::OwnedObjectPath::try_from(format!("/ca/kamacite/BootEnvironments/{id}")).unwrap()
+}
+
+fn environment(id: u32, name: &str, created: i64) -> BootEnvironmentObject {
+    BootEnvironmentObject {
+        path: path(id),
+        name: name.to_string(),
+        description: None,
+        active: false,
+        next_boot: false,
+        boot_once: false,
+        created,
+        mountpoint: None,
+        space: 512 << 20,
+        other_properties: Default::default(),
+        undecoded: Default::default(),
+    }
+}
+
+fn snapshot(env: &str, name: &str, created: i64) -> Snapshot {
+    Snapshot {
+        name: format!("{env}@{name}"),
+        path: format!("zroot/ROOT/{env}@{name}"),
+        description: None,
+        space: 1 << 20,
+        created,
+    }
+}
+
+/// Three boot environments, listed out of creation order: a running default,
+/// an upgrade with a description, and an old one.
+fn backend() -> MemoryBackend {
+    let mut default = environment(1, "default", 200);
+    default.active = true;
+    default.next_boot = true;
+    let mut upgrade = environment(2, "upgrade", 300);
+    upgrade.description = Some("Trying 24.04".to_string());
+    let old = environment(3, "old", 100);
+
+    let snapshots = HashMap::from([(
+        path(1),
+        vec![snapshot("default", "b", 220), snapshot("default", "a", 210)],
+    )]);
+    MemoryBackend::new(vec![default, upgrade, old], snapshots)
+}
+
+#[test]
+fn sizes_are_rounded_before_choosing_a_unit() {
+    // Fluent isolates placeables from the surrounding text.
+    let size = |bytes| format_size(bytes).replace(['\u{2068}', '\u{2069}'], "");
+    assert_eq!(size(512), "512 B");
+    assert_eq!(size(1536), "1.5 KiB");
+    assert_eq!(size(1_048_525), "1.0 MiB");
+    assert_eq!(size(u64::MAX), "16,384.0 PiB");
+}
+
+#[tokio::test]
+async fn connecting_loads_environments_and_snapshots() {
+    let harness = Harness::connected(backend()).await;
+
+    assert_eq!(harness.names(), ["old", "default", "upgrade"]);
+    let snapshots: Vec<&str> = harness.app.snapshots[&path(1)]
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    assert_eq!(snapshots, ["default@a", "default@b"]);
+    assert!(harness.app.snapshots[&path(2)].is_empty());
+}
+
+#[tokio::test]
+async fn dropdowns_follow_boot_flags() {
+    let mut harness = Harness::connected(backend()).await;
+
+    assert_eq!(
+        harness.app.dropdown_labels(),
+        ["old", "default", "Trying 24.04 (upgrade)"]
+    );
+    assert_eq!(harness.app.next_boot_idx(), Some(1));
+    assert_eq!(harness.app.boot_once_idx(), None);
+    assert_eq!(harness.app.total_space(), 3 * (512 << 20));
+
+    harness
+        .send(Message::ActivateEnvironment(path(2), true))
+        .await;
+    assert_eq!(harness.app.next_boot_idx(), Some(1));
+    assert_eq!(harness.app.boot_once_idx(), Some(2));
+
+    // Changing the default cancels the one-time activation.
+    harness
+        .send(Message::ActivateEnvironment(path(3), false))
+        .await;
+    assert_eq!(harness.app.next_boot_idx(), Some(0));
+    assert_eq!(harness.app.boot_once_idx(), None);
+}
+
+#[tokio::test]
+async fn added_environments_are_kept_in_creation_order() {
+    let mut harness = Harness::connected(backend()).await;
+
+    harness.backend.insert(environment(4, "new", 250));
+    let produced = harness.settle().await;
+    assert_eq!(harness.names(), ["old", "default", "new", "upgrade"]);
+    assert!(matches!(
+        &produced[..],
+        [Message::SnapshotsLoaded(loaded, _)] if *loaded == path(4)
+    ));
+
+    // Adding a known path replaces it rather than duplicating it.
+    harness
+        .send(Message::Added(environment(4, "renamed", 250)))
+        .await;
+    assert_eq!(harness.names(), ["old", "default", "renamed", "upgrade"]);
+
+    harness.send(Message::Removed(path(4))).await;
+    assert_eq!(harness.names(), ["old", "default", "upgrade"]);
+}
+
+#[tokio::test]
+async fn removing_the_expanded_environment_collapses_it() {
+    let mut harness = Harness::connected(backend()).await;
+
+    harness.send(Message::ToggleExpanded(path(3))).await;
+    assert_eq!(harness.app.expanded, Some(path(3)));
+
+    harness.send(Message::Removed(path(3))).await;
+    assert_eq!(harness.app.expanded, None);
+    assert!(!harness.app.snapshots.contains_key(&path(3)));
+}
+
+#[tokio::test]
+async fn property_changes_are_applied_in_place() {
+    let mut harness = Harness::connected(backend()).await;
+
+    let produced = harness
+        .send(Message::PropertiesChanged(
+            path(2),
+            BootEnvironmentChanges {
+                description: Some(None),
+                ..Default::default()
+            },
+        ))
+        .await;
+    assert!(produced.is_empty());
+    assert_eq!(harness.environment(2).label(), "upgrade");
+}
+
+#[tokio::test]
+async fn changes_to_unknown_environments_cause_one_reload() {
+    let mut harness = Harness::connected(backend()).await;
+    harness.backend.insert(environment(4, "elsewhere", 400));
+    // Drop the Added event, as if the signal had been missed.
+    while let Some(Some(_)) = harness.events.next().now_or_never() {}
+
+    let task = harness.app.update(Message::PropertiesChanged(
+        path(4),
+        BootEnvironmentChanges::default(),
+    ));
+    assert!(harness.app.reload_pending);
+    // Further changes are folded into the pending reload.
+    let _ = harness.app.update(Message::BootEnvironmentsModified);
+
+    let produced = run(task).await;
+    assert!(matches!(&produced[..], [Message::ReloadBootEnvironments]));
+    harness.send(Message::ReloadBootEnvironments).await;
+    assert!(!harness.app.reload_pending);
+    assert_eq!(harness.names(), ["old", "default", "upgrade", "elsewhere"]);
+}
+
+#[test]
+fn reconnecting_backs_off_with_jitter() {
+    let secs = |failures, jitter| reconnect_delay(failures, jitter).as_secs_f64();
+
+    assert_eq!(secs(0, 0.0), 0.5);
+    assert_eq!(secs(0, 1.0), 1.0);
+    assert_eq!(secs(3, 0.5), 6.0);
+    assert_eq!(secs(10, 1.0), 60.0);
+    assert_eq!(secs(u32::MAX, 0.0), 30.0);
+    for _ in 0..100 {
+        assert!((0.0..1.0).contains(&jitter()));
+    }
+}
+
+#[tokio::test]
+async fn failed_connections_are_retried() {
+    let mut harness = Harness::new(backend());
+    let error = AppError::Disconnected("no bus".to_string());
+
+    // Tasks are dropped rather than run, which would try the real bus.
+    let _retry = harness.app.update(Message::ConnectFailed(error.clone()));
+    assert_eq!(
+        harness.app.connection,
+        ConnectionState::Waiting {
+            failures: 1,
+            error: error.clone()
+        }
+    );
+
+    // Reconnecting by hand skips the wait but not the backoff.
+    let _connect = harness.app.update(Message::Connect);
+    assert_eq!(
+        harness.app.connection,
+        ConnectionState::Connecting { failures: 1 }
+    );
+    let _retry = harness.app.update(Message::ConnectFailed(error.clone()));
+    let _stale = harness.app.update(Message::RetryConnect(1));
+    assert!(matches!(
+        harness.app.connection,
+        ConnectionState::Waiting { failures: 2, .. }
+    ));
+
+    let _connect = harness.app.update(Message::RetryConnect(2));
+    assert_eq!(
+        harness.app.connection,
+        ConnectionState::Connecting { failures: 2 }
+    );
+    harness.connect().await;
+    assert_eq!(harness.app.connection, ConnectionState::Connected);
+    assert_eq!(harness.names(), ["old", "default", "upgrade"]);
+}
+
+#[tokio::test]
+async fn losing_the_connection_holds_actions() {
+    let mut harness = Harness::connected(backend()).await;
+    let generation = harness.app.service_generation;
+
+    let _retry = harness
+        .app
+        .update(Message::ConnectionLost(AppError::Disconnected(
+            "bus restarted".to_string(),
+        )));
+    assert!(harness.app.backend.is_none());
+    assert!(harness.app.environments.is_empty());
+    assert_ne!(harness.app.service_generation, generation);
+    assert!(matches!(
+        harness.app.connection,
+        ConnectionState::Waiting { failures: 0, .. }
+    ));
+
+    let _expiry = harness
+        .app
+        .update(Message::ActivateEnvironment(path(2), false));
+    assert_eq!(harness.app.pending.len(), 1);
+
+    harness.connect().await;
+    assert!(harness.app.pending.is_empty());
+    assert_eq!(harness.app.next_boot_idx(), Some(2));
+}
+
+#[tokio::test]
+async fn actions_wait_for_a_connection() {
+    let mut harness = Harness::new(backend());
+
+    // Don't run the task, which only expires the action.
+    let _expiry = harness
+        .app
+        .update(Message::ActivateEnvironment(path(2), true));
+    let _expiry = harness
+        .app
+        .update(Message::ActivateEnvironment(path(2), true));
+    assert_eq!(harness.app.pending.len(), 1);
+
+    harness.connect().await;
+    assert!(harness.app.pending.is_empty());
+    assert_eq!(harness.app.boot_once_idx(), Some(2));
+}
+
+#[tokio::test]
+async fn confirmed_actions_wait_for_the_service() {
+    let mut harness = Harness::connected(backend()).await;
+    harness.send(Message::ServiceOwnerChanged(false)).await;
+
+    // As if the service went away while the popup was being filled in.
+    harness.app.confirmation = Some(Confirmation::DestroyEnvironment {
+        path: path(3),
+        name: "old".to_string(),
+        typed: "old".to_string(),
+        force_unmount: false,
+        snapshots: false,
+    });
+    let _expiry = harness.app.update(Message::ConfirmAction);
+    assert!(harness.app.confirmation.is_none());
+    assert_eq!(harness.app.pending.len(), 1);
+
+    harness.send(Message::ServiceOwnerChanged(true)).await;
+    assert!(harness.app.pending.is_empty());
+    assert_eq!(harness.names(), ["default", "upgrade"]);
+}
+
+#[tokio::test]
+async fn pending_actions_are_checked_again_after_reloading() {
+    let mut harness = Harness::connected(backend()).await;
+    harness.send(Message::ServiceOwnerChanged(false)).await;
+    harness.app.confirmation = Some(Confirmation::DestroyEnvironment {
+        path: path(3),
+        name: "old".to_string(),
+        typed: "old".to_string(),
+        force_unmount: false,
+        snapshots: false,
+    });
+    let _expiry = harness.app.update(Message::ConfirmAction);
+    assert_eq!(harness.app.pending.len(), 1);
+
+    // The environment is made the default while the service is away.
+    harness.backend.activate(&path(3), false).await.unwrap();
+    harness.send(Message::ServiceOwnerChanged(true)).await;
+
+    assert!(harness.app.pending.is_empty());
+    assert!(harness.app.confirmation.is_none());
+    assert_eq!(harness.names(), ["old", "default", "upgrade"]);
+    assert!(harness.environment(3).next_boot);
+    assert_eq!(harness.app.banners.len(), 1);
+}
+
+#[tokio::test]
+async fn cancelled_actions_are_not_performed() {
+    let mut harness = Harness::new(backend());
+
+    let _expiry = harness
+        .app
+        .update(Message::ActivateEnvironment(path(2), true));
+    let id = harness.app.pending[0].id;
+    harness.send(Message::CancelPending(id)).await;
+
+    harness.connect().await;
+    assert_eq!(harness.app.boot_once_idx(), None);
+}
+
+#[tokio::test(start_paused = true)]
+async fn pending_actions_expire() {
+    let mut harness = Harness::new(backend());
+
+    harness
+        .send(Message::ActivateEnvironment(path(2), false))
+        .await;
+    assert!(harness.app.pending.is_empty());
+    let [banner] = &harness.app.banners[..] else {
+        panic!("expected one banner, got {:?}", harness.app.banners);
+    };
+    assert_eq!(banner.error, AppError::Timeout);
+
+    // Retrying once connected performs the action.
+    let id = banner.id;
+    harness.connect().await;
+    harness.send(Message::RetryBanner(id)).await;
+    assert_eq!(harness.app.next_boot_idx(), Some(2));
+}
+
+#[tokio::test]
+async fn rollbacks_warn_about_snapshots_taken_in_the_same_second() {
+    // Listed out of order, as the service may.
+    let snapshots = HashMap::from([(
+        path(1),
+        vec![
+            snapshot("default", "d", 220),
+            snapshot("default", "b", 220),
+            snapshot("default", "a", 210),
+            snapshot("default", "c", 230),
+        ],
+    )]);
+    let backend = MemoryBackend::new(vec![environment(1, "default", 200)], snapshots);
+    let mut harness = Harness::connected(backend).await;
+
+    harness
+        .send(Message::RequestRollback(path(1), "default@b".to_string()))
+        .await;
+    let Some(Confirmation::Rollback {
+        destroyed,
+        maybe_destroyed,
+        ..
+    }) = &harness.app.confirmation
+    else {
+        panic!("expected a rollback confirmation");
+    };
+    assert_eq!(destroyed, &["default@c"]);
+    assert_eq!(maybe_destroyed, &["default@d"]);
+
+    // The in-memory backend assumes the worst, like the warning.
+    harness.send(Message::ConfirmAction).await;
+    let names: Vec<&str> = harness.app.snapshots[&path(1)]
+        .iter()
+        .map(|s| s.name.as_str())
+        .collect();
+    assert_eq!(names, ["default@a", "default@b"]);
+}
+
+#[tokio::test]
+async fn failures_show_a_banner_with_retry() {
+    let mut harness = Harness::connected(backend()).await;
+
+    harness
+        .send(Message::ActivateEnvironment(path(9), false))
+        .await;
+    let [banner] = &harness.app.banners[..] else {
+        panic!("expected one banner, got {:?}", harness.app.banners);
+    };
+    assert_eq!(banner.error, AppError::NoSuchObject);
+    assert!(matches!(
+        &banner.retry,
+        Some(Message::ActivateEnvironment(retry, false)) if *retry == path(9)
+    ));
+
+    // The same failure again doesn't pile up.
+    harness
+        .send(Message::ActivateEnvironment(path(9), false))
+        .await;
+    assert_eq!(harness.app.banners.len(), 1);
+
+    let id = harness.app.banners[0].id;
+    harness.send(Message::DismissBanner(id)).await;
+    assert!(harness.app.banners.is_empty());
+}
+
+#[tokio::test]
+async fn losing_the_service_forgets_everything() {
+    let mut harness = Harness::connected(backend()).await;
+    harness.send(Message::ToggleExpanded(path(1))).await;
+
+    harness.send(Message::ServiceOwnerChanged(false)).await;
+    assert!(!harness.app.service_available);
+    assert!(harness.app.environments.is_empty());
+    assert!(harness.app.snapshots.is_empty());
+    assert_eq!(harness.app.expanded, None);
+    assert!(harness.app.dropdown_labels().is_empty());
+
+    let generation = harness.app.service_generation;
+    harness.send(Message::ServiceOwnerChanged(true)).await;
+    assert!(harness.app.service_available);
+    assert_eq!(harness.app.service_generation, generation + 1);
+    assert_eq!(harness.names(), ["old", "default", "upgrade"]);
+}
+
+#[tokio::test]
+async fn renames_are_validated_before_they_are_sent() {
+    let mut harness = Harness::connected(backend()).await;
+
+    harness.send(Message::StartRename(path(2))).await;
+    harness
+        .send(Message::EditorInput("default".to_string()))
+        .await;
+    let produced = harness.send(Message::SubmitEditor).await;
+    assert!(produced.is_empty());
+    let editor = harness.app.editor.as_ref().unwrap();
+    assert!(editor.error.is_some());
+    assert!(!editor.in_progress);
+
+    harness
+        .send(Message::EditorInput("noble".to_string()))
+        .await;
+    harness.send(Message::SubmitEditor).await;
+    assert!(harness.app.editor.is_none());
+    assert_eq!(harness.environment(2).name, "noble");
+}
+
+#[tokio::test]
+async fn destroying_an_environment_needs_its_name_typed() {
+    let mut harness = Harness::connected(backend()).await;
+
+    // The running environment can't be destroyed at all.
+    harness
+        .send(Message::RequestDestroyEnvironment(path(1)))
+        .await;
+    assert!(harness.app.confirmation.is_none());
+
+    harness
+        .send(Message::RequestDestroyEnvironment(path(3)))
+        .await;
+    harness.send(Message::ConfirmAction).await;
+    assert!(harness.app.confirmation.is_some());
+
+    harness
+        .send(Message::DestroyConfirmationInput("old".to_string()))
+        .await;
+    harness.send(Message::ConfirmAction).await;
+    assert!(harness.app.confirmation.is_none());
+    assert_eq!(harness.names(), ["default", "upgrade"]);
+}
+
+#[tokio::test]
+async fn environments_scheduled_to_boot_meanwhile_are_not_destroyed() {
+    let mut harness = Harness::connected(backend()).await;
+    harness
+        .send(Message::RequestDestroyEnvironment(path(3)))
+        .await;
+    harness
+        .send(Message::DestroyConfirmationInput("old".to_string()))
+        .await;
+
+    // As if chosen with `beadm` while the user was typing.
+    harness.backend.activate(&path(3), true).await.unwrap();
+    harness.settle().await;
+    harness.send(Message::ConfirmAction).await;
+
+    assert!(harness.app.confirmation.is_none());
+    assert_eq!(harness.names(), ["old", "default", "upgrade"]);
+    assert_eq!(harness.app.banners.len(), 1);
+}
+
+#[tokio::test]
+async fn shutdown_is_blocked_while_environments_are_changed() {
+    let mut harness = Harness::connected(backend()).await;
+
+    harness
+        .send(Message::RequestDestroyEnvironment(path(3)))
+        .await;
+    harness
+        .send(Message::DestroyConfirmationInput("old".to_string()))
+        .await;
+    harness.send(Message::ConfirmAction).await;
+    harness.send(Message::StartRename(path(2))).await;
+    harness
+        .send(Message::EditorInput("noble".to_string()))
+        .await;
+    harness.send(Message::SubmitEditor).await;
+
+    let inhibitions = harness.backend.inhibitions();
+    let [destroy, rename] = &inhibitions[..] else {
+        panic!("expected two inhibitors, got {inhibitions:?}");
+    };
+    assert!(destroy.why.contains("old"), "{destroy:?}");
+    assert!(rename.why.contains("noble"), "{rename:?}");
+    // Both were released once the calls finished.
+    assert!(harness
+        .backend
+        .shutdown_inhibitors()
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn rebooting_is_offered_after_choosing_another_environment() {
+    let mut harness = Harness::connected(backend()).await;
+    assert!(harness.app.can_reboot);
+    assert!(harness.app.reboot_target().is_none());
+
+    harness
+        .send(Message::ActivateEnvironment(path(2), false))
+        .await;
+    assert_eq!(harness.app.reboot_target().unwrap().path, path(2));
+
+    // Booting once takes precedence over the default.
+    harness
+        .send(Message::ActivateEnvironment(path(3), true))
+        .await;
+    assert_eq!(harness.app.reboot_target().unwrap().path, path(3));
+}
+
+#[tokio::test(start_paused = true)]
+async fn the_reboot_countdown_ends_in_a_reboot() {
+    let mut harness = Harness::connected(backend()).await;
+    harness
+        .send(Message::ActivateEnvironment(path(2), false))
+        .await;
+
+    let produced = harness.send(Message::RequestReboot).await;
+    let ticks = produced
+        .iter()
+        .filter(|message| matches!(message, Message::RebootTick(_)))
+        .count();
+    assert_eq!(ticks, REBOOT_COUNTDOWN as usize);
+    assert!(harness.app.reboot.is_none());
+    assert_eq!(harness.backend.reboots(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn cancelling_the_reboot_countdown_prevents_the_reboot() {
+    let mut harness = Harness::connected(backend()).await;
+    harness.backend.set_inhibitors(vec![Inhibitor {
+        what: "shutdown:sleep".to_string(),
+        who: "Updater".to_string(),
+        why: "Installing updates".to_string(),
+        mode: "block".to_string(),
+        uid: 1000,
+        pid: 42,
+    }]);
+    harness
+        .send(Message::ActivateEnvironment(path(2), false))
+        .await;
+
+    // Hold back the first tick while the inhibitors are listed.
+    let (ticks, others): (Vec<_>, Vec<_>) = run(harness.app.update(Message::RequestReboot))
+        .await
+        .into_iter()
+        .partition(|message| matches!(message, Message::RebootTick(_)));
+    harness.process(others.into()).await;
+    let countdown = harness.app.reboot.as_ref().unwrap();
+    assert_eq!(countdown.remaining, REBOOT_COUNTDOWN);
+    assert_eq!(countdown.inhibitors[0].who, "Updater");
+
+    harness.send(Message::CancelReboot).await;
+    harness.process(ticks.into()).await;
+    assert!(harness.app.reboot.is_none());
+    assert_eq!(harness.backend.reboots(), 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn rebooting_is_checked_again_before_counting_down() {
+    let mut harness = Harness::connected(backend()).await;
+    harness
+        .send(Message::ActivateEnvironment(path(2), false))
+        .await;
+    assert!(harness.app.can_reboot);
+
+    // Policy changed after connecting, with the popup already open.
+    harness.backend.set_can_reboot(false);
+    harness.app.popup = Some(Id::unique());
+    harness.send(Message::ShowRebootCountdown).await;
+    assert!(!harness.app.can_reboot);
+    assert!(harness.app.reboot.is_none());
+    assert_eq!(harness.backend.reboots(), 0);
+}
+
+/// The environments of [`backend`], after booting into the upgrade once.
+fn trial_backend() -> MemoryBackend {
+    let mut default = environment(1, "default", 200);
+    default.next_boot = true;
+    let mut upgrade = environment(2, "upgrade", 300);
+    upgrade.active = true;
+    MemoryBackend::new(vec![default, upgrade], HashMap::new())
+}
+
+/// An applet that asked to boot into `upgrade` once, after rebooting into it.
+async fn trial_harness() -> Harness {
+    let mut harness = Harness::new(trial_backend());
+    harness.app.state.boot_once = Some(path(2).to_string());
+    harness.connect().await;
+    harness
+}
+
+#[tokio::test]
+async fn temporarily_booted_environments_can_be_kept() {
+    let mut harness = trial_harness().await;
+    assert_eq!(harness.app.trial_environment().unwrap().path, path(2));
+
+    harness.send(Message::KeepTrial).await;
+    assert!(harness.environment(2).next_boot);
+    assert!(harness.app.trial_environment().is_none());
+    assert!(harness.app.reboot_target().is_none());
+}
+
+#[tokio::test]
+async fn temporarily_booted_environments_can_be_left() {
+    let mut harness = trial_harness().await;
+
+    harness.send(Message::EndTrial).await;
+    assert!(harness.app.trial_environment().is_none());
+    assert_eq!(harness.app.state.boot_once, None);
+    assert!(harness.environment(1).next_boot);
+    assert_eq!(harness.app.reboot_target().unwrap().path, path(1));
+
+    // Reloading doesn't bring the question back.
+    harness.send(Message::ReloadBootEnvironments).await;
+    assert!(harness.app.trial_environment().is_none());
+}
+
+#[tokio::test]
+async fn changing_the_default_is_not_a_trial() {
+    let mut harness = Harness::connected(backend()).await;
+
+    harness
+        .send(Message::ActivateEnvironment(path(2), false))
+        .await;
+    harness.send(Message::ReloadBootEnvironments).await;
+    assert!(harness.environment(1).is_temporarily_booted());
+    assert!(harness.app.trial_environment().is_none());
+}
+
+#[tokio::test]
+async fn changing_the_default_without_rebooting_is_not_a_trial() {
+    // The running environment is no longer the default, but it was never
+    // seen scheduled to boot once.
+    let mut harness = Harness::connected(trial_backend()).await;
+    assert!(harness.environment(2).is_temporarily_booted());
+    assert!(harness.app.trial_environment().is_none());
+
+    let notifier = harness.notifier().await;
+    assert!(notifier.sent().is_empty());
+}
+
+#[tokio::test]
+async fn booting_once_is_remembered_until_it_plays_out() {
+    let mut harness = Harness::connected(backend()).await;
+    harness
+        .send(Message::ActivateEnvironment(path(2), true))
+        .await;
+    assert_eq!(harness.app.state.boot_once, Some(path(2).to_string()));
+
+    // Logging in again before rebooting keeps it remembered.
+    let state = harness.app.state.clone();
+    let mut harness = Harness::new(MemoryBackend::new(
+        harness.app.environments.clone(),
+        HashMap::new(),
+    ));
+    harness.app.state = state;
+    harness.connect().await;
+    assert_eq!(harness.app.state.boot_once, Some(path(2).to_string()));
+}
+
+#[tokio::test]
+async fn booting_once_from_elsewhere_is_remembered() {
+    // As if with `beadm activate -t`.
+    let mut harness = Harness::connected(backend()).await;
+    harness.backend.activate(&path(2), true).await.unwrap();
+    harness.settle().await;
+    assert_eq!(harness.app.state.boot_once, Some(path(2).to_string()));
+
+    // After rebooting into it, it's offered to be kept.
+    let state = harness.app.state.clone();
+    let mut harness = Harness::new(trial_backend());
+    harness.app.state = state;
+    harness.connect().await;
+    assert_eq!(harness.app.trial_environment().unwrap().path, path(2));
+}
+
+/// The action keys of a notification.
+fn action_keys(notification: &Notification) -> Vec<&str> {
+    notification
+        .actions
+        .iter()
+        .map(|(key, _)| key.as_str())
+        .collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn changes_made_elsewhere_are_notified_and_can_be_undone() {
+    let mut harness = Harness::connected(backend()).await;
+    let notifier = harness.notifier().await;
+
+    harness.backend.activate(&path(2), false).await.unwrap();
+    harness.settle().await;
+    let [(id, notification)] = &notifier.sent()[..] else {
+        panic!("expected one notification, got {:?}", notifier.sent());
+    };
+    assert_eq!(action_keys(notification), ["undo", "reboot"]);
+
+    harness
+        .send(Message::NotificationAction(*id, "undo".to_string()))
+        .await;
+    assert!(harness.environment(1).next_boot);
+
+    // Undoing is asked for here, so it isn't news, even with the popup
+    // closed.
+    harness.settle().await;
+    assert_eq!(notifier.sent().len(), 1);
+
+    // Changes made elsewhere afterwards still are.
+    harness.backend.activate(&path(3), true).await.unwrap();
+    harness.settle().await;
+    let sent = notifier.sent();
+    assert_eq!(sent.len(), 2);
+    assert_eq!(sent[1].1.replaces, Some(*id));
+}
+
+#[tokio::test(start_paused = true)]
+async fn bursts_of_changes_cause_one_notification() {
+    let mut harness = Harness::connected(backend()).await;
+    let notifier = harness.notifier().await;
+
+    harness.backend.activate(&path(2), false).await.unwrap();
+    harness.backend.activate(&path(3), false).await.unwrap();
+    harness.backend.insert(environment(4, "new", 400));
+    harness.settle().await;
+    let [(_, notification)] = &notifier.sent()[..] else {
+        panic!("expected one notification, got {:?}", notifier.sent());
+    };
+    assert_eq!(notification.body.lines().count(), 2);
+
+    // Changes that cancel out go unmentioned.
+    harness.backend.activate(&path(1), true).await.unwrap();
+    harness.backend.activate(&path(3), false).await.unwrap();
+    harness.settle().await;
+    assert_eq!(notifier.sent().len(), 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn notifications_can_be_turned_off() {
+    let mut harness = Harness::connected(backend()).await;
+    let notifier = harness.notifier().await;
+    harness.send(Message::SetNotifyBootChanges(false)).await;
+
+    harness.backend.activate(&path(2), true).await.unwrap();
+    harness.settle().await;
+    assert!(notifier.sent().is_empty());
+
+    harness.backend.insert(environment(4, "new", 400));
+    harness.settle().await;
+    let [(_, notification)] = &notifier.sent()[..] else {
+        panic!("expected one notification, got {:?}", notifier.sent());
+    };
+    assert!(action_keys(notification).is_empty());
+}
+
+#[tokio::test(start_paused = true)]
+async fn temporarily_booted_environments_are_notified_once() {
+    let mut harness = trial_harness().await;
+    let notifier = harness.notifier().await;
+
+    let [(id, notification)] = &notifier.sent()[..] else {
+        panic!("expected one notification, got {:?}", notifier.sent());
+    };
+    assert_eq!(action_keys(notification), ["keep", "go-back"]);
+
+    harness.send(Message::ServiceOwnerChanged(true)).await;
+    assert_eq!(notifier.sent().len(), 1);
+
+    harness
+        .send(Message::NotificationAction(*id, "keep".to_string()))
+        .await;
+    assert!(harness.environment(2).next_boot);
+    harness.settle().await;
+    assert_eq!(notifier.sent().len(), 1);
+}
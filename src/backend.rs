@@ -0,0 +1,187 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Abstracts over where boot environments come from, so that the applet can be
+//! driven by something other than the live D-Bus service.
+
+use futures_util::future::BoxFuture;
+use futures_util::stream::BoxStream;
+use zbus::zvariant;
+
+use crate::error::AppError;
+use crate::login1::Inhibitor;
+use crate::model::{BootEnvironmentChanges, BootEnvironmentObject, Snapshot};
+use crate::packagekit::PackageTransaction;
+
+mod dbus;
+mod memory;
+
+pub use dbus::DBusBackend;
+pub use memory::MemoryBackend;
+
+/// The environment variable that selects the in-memory demo backend when set
+/// to `memory`.
+pub const BACKEND_ENV: &str = "COSMIC_BOOT_ENVIRONMENT_BACKEND";
+
+/// A change to the set of boot environments, as reported by
+/// [`Backend::watch`].
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A boot environment appeared.
+    Added(BootEnvironmentObject),
+    /// A boot environment went away.
+    Removed(zvariant::OwnedObjectPath),
+    /// Some properties of a boot environment changed.
+    Changed(zvariant::OwnedObjectPath, BootEnvironmentChanges),
+    /// Something changed that can't be described precisely, so everything
+    /// should be reloaded.
+    Modified,
+}
+
+/// Keeps shutting down and sleeping blocked until dropped. Returned by
+/// [`Backend::inhibit_shutdown`].
+#[must_use = "shutdown is only blocked until the lock is dropped"]
+pub struct ShutdownLock {
+    _held: Box<dyn Send + Sync>,
+}
+
+impl ShutdownLock {
+    /// Block shutdown for as long as `held` is alive.
+    pub(crate) fn new(held: impl Send + Sync + 'static) -> Self {
+        ShutdownLock {
+            _held: Box::new(held),
+        }
+    }
+}
+
+impl std::fmt::Debug for ShutdownLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShutdownLock").finish_non_exhaustive()
+    }
+}
+
+/// A source of boot environments and the operations on them, including
+/// rebooting into them and naming the package updates that call for a
+/// snapshot.
+///
+/// Boot environments and their snapshots are identified by object path, as on
+/// the bus, whatever the implementation.
+pub trait Backend: std::fmt::Debug + Send + Sync {
+    /// List all boot environments, in no particular order.
+    fn list(&self) -> BoxFuture<'_, Result<Vec<BootEnvironmentObject>, AppError>>;
+
+    /// A stream of whether the backend is available, reported each time it
+    /// appears or goes away.
+    fn watch_service(&self) -> BoxStream<'static, bool>;
+
+    /// A stream of changes to the boot environments.
+    fn watch(&self) -> BoxStream<'static, Event>;
+
+    /// Resolve once the connection to the backend has been lost for good,
+    /// with the reason. A new backend is needed to carry on.
+    fn closed(&self) -> BoxFuture<'static, AppError>;
+
+    /// List the snapshots of a boot environment, in no particular order.
+    ///
+    /// Creation times only have a resolution of a second, so snapshots taken
+    /// in the same second can't be put in order.
+    fn snapshots<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+    ) -> BoxFuture<'a, Result<Vec<Snapshot>, AppError>>;
+
+    /// Activate a boot environment, either for the next boot only or
+    /// permanently.
+    fn activate<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        temporary: bool,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// Snapshot a boot environment, returning the name of the new snapshot.
+    fn snapshot<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        name: &'a str,
+        description: &'a str,
+    ) -> BoxFuture<'a, Result<String, AppError>>;
+
+    /// Roll a boot environment back to one of its snapshots, destroying any
+    /// later ones.
+    fn rollback<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        snapshot: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// Destroy one snapshot of a boot environment.
+    fn destroy_snapshot<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        snapshot: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// Rename a boot environment.
+    fn rename<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        new_name: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// Set the description of a boot environment. An empty description
+    /// clears it.
+    fn describe<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        description: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// Destroy a boot environment, optionally unmounting it first and taking
+    /// its snapshots with it.
+    fn destroy<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        force_unmount: bool,
+        snapshots: bool,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// Mount a boot environment at an existing directory.
+    fn mount<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        mountpoint: &'a str,
+        read_only: bool,
+    ) -> BoxFuture<'a, Result<(), AppError>>;
+
+    /// Unmount a boot environment, returning its former mountpoint.
+    fn unmount<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        force: bool,
+    ) -> BoxFuture<'a, Result<String, AppError>>;
+
+    /// Whether the user may reboot the system, perhaps after authenticating.
+    fn can_reboot(&self) -> BoxFuture<'_, Result<bool, AppError>>;
+
+    /// List the programs currently blocking shutdown.
+    fn shutdown_inhibitors(&self) -> BoxFuture<'_, Result<Vec<Inhibitor>, AppError>>;
+
+    /// Reboot the system, asking the user to authenticate if needed.
+    fn reboot(&self) -> BoxFuture<'_, Result<(), AppError>>;
+
+    /// Block shutting down and sleeping until the returned lock is dropped.
+    /// `who` and `why` are shown to the user if they try anyway.
+    fn inhibit_shutdown<'a>(
+        &'a self,
+        who: &'a str,
+        why: &'a str,
+    ) -> BoxFuture<'a, Result<ShutdownLock, AppError>>;
+
+    /// The package transaction that is updating the system, if any, for
+    /// naming snapshots taken on its behalf. Nothing is reported if it can't
+    /// be told, as the snapshot is worth more than its description.
+    fn update_transaction(&self) -> BoxFuture<'_, Option<PackageTransaction>>;
+}
@@ -0,0 +1,797 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! The boot environment service on the system bus.
+
+use std::collections::HashMap;
+use std::future::Future;
+
+use futures_util::future::BoxFuture;
+use futures_util::stream::{self, BoxStream};
+use futures_util::{FutureExt, StreamExt};
+use zbus::fdo::ObjectManagerProxy;
+use zbus::zvariant;
+
+use super::{Backend, Event, ShutdownLock};
+use crate::dbus::BootEnvironmentProxy;
+use crate::error::AppError;
+use crate::login1::{Inhibitor, ManagerProxy};
+use crate::model::{BootEnvironmentChanges, BootEnvironmentObject, PropertyIssue, Snapshot};
+use crate::packagekit::{PackageKitProxy, PackageTransaction, TransactionProxy};
+
+/// The well-known bus name of the boot environment service.
+const SERVICE_NAME: &str = "ca.kamacite.BootEnvironments1";
+
+/// The path of the service's object manager, under which all boot
+/// environments live.
+const OBJECT_MANAGER_PATH: &str = "/ca/kamacite/BootEnvironments";
+
+/// The interface implemented by each boot environment object.
+const INTERFACE: &str = "ca.kamacite.BootEnvironment";
+
+/// Talks to the boot environment service over D-Bus.
+#[derive(Debug, Clone)]
+pub struct DBusBackend {
+    conn: zbus::Connection,
+}
+
+impl DBusBackend {
+    /// Connect to the boot environment service on the system bus.
+    pub async fn system() -> Result<Self, AppError> {
+        let conn = zbus::Connection::system().await?;
+        tracing::info!(
+            unique_name = conn
+                .unique_name()
+                .map(std::string::ToString::to_string)
+                .unwrap_or_default(),
+            "Connected to system bus"
+        );
+        Ok(DBusBackend::new(conn))
+    }
+
+    /// Use the boot environment service on an existing connection.
+    pub fn new(conn: zbus::Connection) -> Self {
+        DBusBackend { conn }
+    }
+
+    /// Create a proxy for the service's object manager.
+    async fn object_manager(&self) -> Result<ObjectManagerProxy<'static>, zbus::Error> {
+        ObjectManagerProxy::builder(&self.conn)
+            .destination(SERVICE_NAME)?
+            .path(OBJECT_MANAGER_PATH)?
+            .build()
+            .await
+    }
+
+    /// Create a proxy for a boot environment by its object path.
+    async fn proxy<'a>(
+        &self,
+        path: &'a zvariant::OwnedObjectPath,
+    ) -> Result<BootEnvironmentProxy<'a>, zbus::Error> {
+        BootEnvironmentProxy::builder(&self.conn)
+            .path(path)?
+            .build()
+            .await
+    }
+
+    /// Create a proxy for logind, which handles rebooting.
+    async fn login1(&self) -> Result<ManagerProxy<'static>, zbus::Error> {
+        ManagerProxy::new(&self.conn).await
+    }
+
+    /// Read what a PackageKit transaction is going to do, or `None` if it
+    /// won't update the system or has already gone away.
+    async fn package_transaction(&self, id: String) -> Option<PackageTransaction> {
+        let result = async {
+            let transaction = TransactionProxy::builder(&self.conn)
+                .path(id.clone())?
+                .cache_properties(zbus::proxy::CacheProperties::No)
+                .build()
+                .await?;
+            let role = transaction.role().await?;
+            let flags = transaction.transaction_flags().await?;
+            Ok::<_, zbus::Error>((role, flags))
+        }
+        .await;
+        match result {
+            Ok((role, flags)) => PackageTransaction::new(id, role, flags),
+            Err(e) => {
+                tracing::debug!(error = ?e, id, "Couldn't read PackageKit transaction");
+                None
+            }
+        }
+    }
+
+    /// Query boot environments from the object manager.
+    async fn load_boot_environments(&self) -> Result<Vec<BootEnvironmentObject>, zbus::Error> {
+        let mut environments = Vec::new();
+        for (path, interfaces) in self.object_manager().await?.get_managed_objects().await? {
+            if let Some(props) = interfaces.get(INTERFACE) {
+                environments.push(parse_boot_environment(path, props));
+            }
+        }
+        Ok(environments)
+    }
+
+    /// A stream of whether the service has an owner on the bus.
+    async fn service_events(&self) -> Result<BoxStream<'static, bool>, zbus::Error> {
+        let dbus = zbus::fdo::DBusProxy::new(&self.conn).await?;
+        let stream = dbus
+            .receive_name_owner_changed_with_args(&[(0, SERVICE_NAME)])
+            .await?;
+        Ok(stream
+            .filter_map(|signal| async move {
+                let args = signal.args().ok()?;
+                tracing::debug!(
+                    old_owner = ?args.old_owner(),
+                    new_owner = ?args.new_owner(),
+                    "Boot environment service owner changed"
+                );
+                // A handover between owners is reported as a single signal;
+                // treat it as the service restarting.
+                Some(args.new_owner().is_some())
+            })
+            .boxed())
+    }
+
+    /// A stream of `Added` and `Removed` events from the object manager.
+    async fn object_manager_events(&self) -> Result<BoxStream<'static, Event>, zbus::Error> {
+        let object_manager = self.object_manager().await?;
+
+        let added =
+            object_manager
+                .receive_interfaces_added()
+                .await?
+                .filter_map(|signal| async move {
+                    let args = signal.args().ok()?;
+                    let props = args.interfaces_and_properties.get(INTERFACE)?;
+                    let path = From::from(args.object_path);
+                    Some(Event::Added(parse_boot_environment(path, props)))
+                });
+
+        let removed = object_manager
+            .receive_interfaces_removed()
+            .await?
+            .filter_map(|signal| async move {
+                let args = signal.args().ok()?;
+                Some(Event::Removed(From::from(args.object_path)))
+            });
+
+        Ok(stream::select(added, removed).boxed())
+    }
+
+    /// Find a PackageKit transaction that is updating the system, among
+    /// those in progress.
+    async fn find_update_transaction(&self) -> Result<Option<PackageTransaction>, zbus::Error> {
+        let packagekit = PackageKitProxy::new(&self.conn).await?;
+        for id in packagekit.get_transaction_list().await? {
+            let transaction = self.package_transaction(id.to_string()).await;
+            if transaction.is_some() {
+                return Ok(transaction);
+            }
+        }
+        Ok(None)
+    }
+
+    /// A stream of events for `PropertiesChanged` signals from all boot
+    /// environments.
+    async fn properties_changed_events(&self) -> Result<BoxStream<'static, Event>, zbus::Error> {
+        // Match against all PropertiesChanged signals in the boot environment
+        // namespace.
+        let rule = zbus::MatchRule::builder()
+            .msg_type(zbus::message::Type::Signal)
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path_namespace(OBJECT_MANAGER_PATH)?
+            .build();
+
+        let stream = zbus::MessageStream::for_match_rule(rule, &self.conn, Some(32)).await?;
+        Ok(stream
+            .filter_map(|msg_result| async move {
+                match msg_result {
+                    Ok(msg) => properties_changed_event(&msg),
+                    Err(e) => {
+                        tracing::error!(error = ?e, "Error receiving PropertiesChanged signal");
+                        None
+                    }
+                }
+            })
+            .boxed())
+    }
+}
+
+/// Turn a future that sets up a stream into the stream itself. If setting up
+/// fails the stream just ends, so it is logged as an error.
+fn flatten_stream<T: Send + 'static>(
+    setup: impl Future<Output = Result<BoxStream<'static, T>, zbus::Error>> + Send + 'static,
+    what: &'static str,
+) -> BoxStream<'static, T> {
+    setup
+        .map(move |result| {
+            result.unwrap_or_else(|e| {
+                tracing::error!(error = ?e, "Failed to start streaming {what}, updates will be ignored");
+                stream::empty().boxed()
+            })
+        })
+        .flatten_stream()
+        .boxed()
+}
+
+/// Decode a boot environment, with one warning covering any properties that
+/// fell back to defaults.
+fn parse_boot_environment<'a, K, V>(
+    path: zvariant::OwnedObjectPath,
+    props: &'a HashMap<K, V>,
+) -> BootEnvironmentObject
+where
+    K: std::borrow::Borrow<str> + Eq + std::hash::Hash,
+    V: std::borrow::Borrow<zvariant::Value<'a>>,
+{
+    let (env, issues) = BootEnvironmentObject::from_properties(path, props);
+    if !issues.is_empty() {
+        tracing::warn!(
+            path = env.path.to_string(),
+            issues = join_issues(&issues),
+            "Using defaults for boot environment properties that couldn't be decoded"
+        );
+    }
+    env
+}
+
+fn join_issues(issues: &[PropertyIssue]) -> String {
+    issues
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Turn a `PropertiesChanged` signal into an event that applies the changes
+/// directly where possible. Signals for other interfaces are ignored.
+///
+/// Anything we can't apply precisely -- invalidated properties, changed
+/// properties that can't be decoded or a signal we can't parse -- falls back
+/// to reloading everything, which sidesteps our getting out of sync with the
+/// backend.
+fn properties_changed_event(msg: &zbus::Message) -> Option<Event> {
+    let header = msg.header();
+    let Some(path) = header.path() else {
+        return Some(Event::Modified);
+    };
+    let path = zvariant::OwnedObjectPath::from(path.clone());
+
+    let body = msg.body();
+    let (iface, changed, invalidated) =
+        match body.deserialize::<(String, HashMap<String, zvariant::Value<'_>>, Vec<String>)>() {
+            Ok(args) => args,
+            Err(e) => {
+                tracing::error!(error = ?e, "Failed to parse PropertiesChanged signal");
+                return Some(Event::Modified);
+            }
+        };
+
+    let props: Vec<&str> = changed.keys().map(String::as_str).collect();
+    tracing::debug!(
+        path = path.to_string(),
+        iface,
+        props = props.join(","),
+        invalidated = invalidated.join(","),
+        "One or more BootEnvironment properties updated"
+    );
+
+    if iface != INTERFACE {
+        return None;
+    }
+    if !invalidated.is_empty() {
+        return Some(Event::Modified);
+    }
+
+    let (changes, issues) = BootEnvironmentChanges::from_properties(&changed);
+    if !issues.is_empty() {
+        tracing::warn!(
+            path = path.to_string(),
+            issues = join_issues(&issues),
+            "Reloading after changed properties that couldn't be decoded"
+        );
+        return Some(Event::Modified);
+    }
+    Some(Event::Changed(path, changes))
+}
+
+impl Backend for DBusBackend {
+    fn list(&self) -> BoxFuture<'_, Result<Vec<BootEnvironmentObject>, AppError>> {
+        Box::pin(async move { Ok(self.load_boot_environments().await?) })
+    }
+
+    fn watch_service(&self) -> BoxStream<'static, bool> {
+        let this = self.clone();
+        flatten_stream(
+            async move { this.service_events().await },
+            "NameOwnerChanged signal",
+        )
+    }
+
+    fn watch(&self) -> BoxStream<'static, Event> {
+        let this = self.clone();
+        let object_manager = flatten_stream(
+            async move { this.object_manager_events().await },
+            "ObjectManager signals",
+        );
+        let this = self.clone();
+        let properties_changed = flatten_stream(
+            async move { this.properties_changed_events().await },
+            "PropertiesChanged signals",
+        );
+        stream::select(object_manager, properties_changed).boxed()
+    }
+
+    fn closed(&self) -> BoxFuture<'static, AppError> {
+        // zbus reports a failure to read from the socket on every message
+        // stream, and then ends them.
+        let mut messages = zbus::MessageStream::from(&self.conn);
+        Box::pin(async move {
+            while let Some(message) = messages.next().await {
+                if let Err(e) = message {
+                    return AppError::Disconnected(e.to_string());
+                }
+            }
+            AppError::Disconnected("the connection was closed".to_string())
+        })
+    }
+
+    fn snapshots<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+    ) -> BoxFuture<'a, Result<Vec<Snapshot>, AppError>> {
+        Box::pin(async move {
+            let snapshots = self.proxy(path).await?.get_snapshots().await?;
+            Ok(snapshots.into_iter().map(Snapshot::from).collect())
+        })
+    }
+
+    fn activate<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        temporary: bool,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move { Ok(self.proxy(path).await?.activate(temporary).await?) })
+    }
+
+    fn snapshot<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        name: &'a str,
+        description: &'a str,
+    ) -> BoxFuture<'a, Result<String, AppError>> {
+        Box::pin(async move { Ok(self.proxy(path).await?.snapshot(name, description).await?) })
+    }
+
+    fn rollback<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        snapshot: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move { Ok(self.proxy(path).await?.rollback(snapshot).await?) })
+    }
+
+    fn destroy_snapshot<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        snapshot: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move { Ok(self.proxy(path).await?.destroy_snapshot(snapshot).await?) })
+    }
+
+    fn rename<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        new_name: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move { Ok(self.proxy(path).await?.rename(new_name).await?) })
+    }
+
+    fn describe<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        description: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move { Ok(self.proxy(path).await?.describe(description).await?) })
+    }
+
+    fn destroy<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        force_unmount: bool,
+        snapshots: bool,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            Ok(self
+                .proxy(path)
+                .await?
+                .destroy(force_unmount, snapshots)
+                .await?)
+        })
+    }
+
+    fn mount<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        mountpoint: &'a str,
+        read_only: bool,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move { Ok(self.proxy(path).await?.mount(mountpoint, read_only).await?) })
+    }
+
+    fn unmount<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        force: bool,
+    ) -> BoxFuture<'a, Result<String, AppError>> {
+        Box::pin(async move { Ok(self.proxy(path).await?.unmount(force).await?) })
+    }
+
+    fn can_reboot(&self) -> BoxFuture<'_, Result<bool, AppError>> {
+        Box::pin(async move {
+            let answer = self.login1().await?.can_reboot().await?;
+            // "challenge" means that polkit will ask for a password.
+            Ok(matches!(answer.as_str(), "yes" | "challenge"))
+        })
+    }
+
+    fn shutdown_inhibitors(&self) -> BoxFuture<'_, Result<Vec<Inhibitor>, AppError>> {
+        Box::pin(async move {
+            let inhibitors = self.login1().await?.list_inhibitors().await?;
+            Ok(inhibitors
+                .into_iter()
+                .map(Inhibitor::from)
+                .filter(Inhibitor::blocks_shutdown)
+                .collect())
+        })
+    }
+
+    fn reboot(&self) -> BoxFuture<'_, Result<(), AppError>> {
+        Box::pin(async move { Ok(self.login1().await?.reboot(true).await?) })
+    }
+
+    fn inhibit_shutdown<'a>(
+        &'a self,
+        who: &'a str,
+        why: &'a str,
+    ) -> BoxFuture<'a, Result<ShutdownLock, AppError>> {
+        Box::pin(async move {
+            // logind lifts the block once every copy of the descriptor is
+            // closed.
+            let fd = self
+                .login1()
+                .await?
+                .inhibit("shutdown:sleep", who, why, "block")
+                .await?;
+            Ok(ShutdownLock::new(fd))
+        })
+    }
+
+    fn update_transaction(&self) -> BoxFuture<'_, Option<PackageTransaction>> {
+        Box::pin(async move {
+            // Updates are also installed without PackageKit, and it needn't
+            // be running at all.
+            match self.find_update_transaction().await {
+                Ok(transaction) => transaction,
+                Err(e) => {
+                    tracing::debug!(error = ?e, "Couldn't list PackageKit transactions");
+                    None
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::mock_service::{MockService, TestBus, FIXTURE};
+    use crate::packagekit::UpgradeKind;
+
+    /// How long to wait for a signal before giving up.
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    async fn setup() -> (TestBus, MockService, DBusBackend) {
+        let bus = TestBus::start().expect("dbus-daemon is needed to run D-Bus tests");
+        let service = MockService::start(&bus, FIXTURE).await.unwrap();
+        let backend = DBusBackend::new(bus.connect().await.unwrap());
+        (bus, service, backend)
+    }
+
+    async fn next<T>(stream: &mut BoxStream<'static, T>) -> T {
+        tokio::time::timeout(TIMEOUT, stream.next())
+            .await
+            .expect("timed out waiting for a signal")
+            .expect("stream ended")
+    }
+
+    async fn find(backend: &DBusBackend, name: &str) -> BootEnvironmentObject {
+        let environments = backend.list().await.unwrap();
+        environments
+            .into_iter()
+            .find(|env| env.name == name)
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn list_loads_all_properties() {
+        let (_bus, _service, backend) = setup().await;
+
+        let mut environments = backend.list().await.unwrap();
+        environments.sort_by_key(|env| env.created);
+        let names: Vec<&str> = environments.iter().map(|env| env.name.as_str()).collect();
+        assert_eq!(names, ["old", "default", "upgrade"]);
+
+        let [old, default, upgrade] = &environments[..] else {
+            unreachable!();
+        };
+        assert_eq!(
+            default.path.as_str(),
+            "/ca/kamacite/BootEnvironments/2273ae3fef032263"
+        );
+        assert!(default.active && default.next_boot && !default.boot_once);
+        assert_eq!(default.description, None);
+        assert_eq!(default.space, 8 << 30);
+        assert_eq!(upgrade.description.as_deref(), Some("Trying out 24.04"));
+        assert!(!upgrade.active && !upgrade.next_boot);
+        assert_eq!(
+            old.mountpoint.as_deref(),
+            Some("/run/user/1000/cosmic-applet-boot-environment/old")
+        );
+        assert_eq!(upgrade.mountpoint, None);
+    }
+
+    #[tokio::test]
+    async fn snapshots_are_decoded() {
+        let (_bus, _service, backend) = setup().await;
+        let default = find(&backend, "default").await;
+
+        let snapshots = backend.snapshots(&default.path).await.unwrap();
+        let names: Vec<&str> = snapshots.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "default@2024-06-01",
+                "default@before-upgrade",
+                "default@2024-08-01"
+            ]
+        );
+        assert_eq!(snapshots[0].description, None);
+        assert_eq!(
+            snapshots[1].description.as_deref(),
+            Some("Before upgrading to 24.04")
+        );
+        assert_eq!(snapshots[1].path, "zroot/ROOT/default@before-upgrade");
+        assert_eq!(snapshots[1].space, 128 << 20);
+    }
+
+    #[tokio::test]
+    async fn activation_is_signalled_as_property_changes() {
+        let (_bus, _service, backend) = setup().await;
+        let default = find(&backend, "default").await;
+        let upgrade = find(&backend, "upgrade").await;
+        let mut events = backend.properties_changed_events().await.unwrap();
+
+        backend.activate(&upgrade.path, false).await.unwrap();
+
+        let mut next_boot = HashMap::new();
+        for _ in 0..2 {
+            match next(&mut events).await {
+                Event::Changed(path, changes) => {
+                    next_boot.insert(path, changes.next_boot.unwrap());
+                }
+                event => panic!("unexpected event: {event:?}"),
+            }
+        }
+        assert_eq!(next_boot.get(&default.path), Some(&false));
+        assert_eq!(next_boot.get(&upgrade.path), Some(&true));
+    }
+
+    #[tokio::test]
+    async fn renames_and_descriptions_are_signalled() {
+        let (_bus, _service, backend) = setup().await;
+        let upgrade = find(&backend, "upgrade").await;
+        let mut events = backend.properties_changed_events().await.unwrap();
+
+        backend.describe(&upgrade.path, "").await.unwrap();
+        let Event::Changed(path, changes) = next(&mut events).await else {
+            panic!("expected a property change");
+        };
+        assert_eq!(path, upgrade.path);
+        assert_eq!(changes.description, Some(None));
+
+        backend.rename(&upgrade.path, "noble").await.unwrap();
+        let Event::Changed(_, changes) = next(&mut events).await else {
+            panic!("expected a property change");
+        };
+        assert_eq!(changes.name.as_deref(), Some("noble"));
+        let snapshots = backend.snapshots(&upgrade.path).await.unwrap();
+        assert_eq!(snapshots[0].name, "noble@installed");
+    }
+
+    #[test]
+    fn undecodable_changes_cause_a_reload() {
+        let signal = |value: zvariant::Value<'_>| {
+            zbus::Message::signal(
+                "/ca/kamacite/BootEnvironments/0",
+                "org.freedesktop.DBus.Properties",
+                "PropertiesChanged",
+            )
+            .unwrap()
+            .build(&(
+                INTERFACE,
+                HashMap::from([("NextBoot", value)]),
+                Vec::<&str>::new(),
+            ))
+            .unwrap()
+        };
+
+        let event = properties_changed_event(&signal(zvariant::Value::from(true)));
+        assert!(
+            matches!(&event, Some(Event::Changed(_, changes)) if changes.next_boot == Some(true)),
+            "{event:?}"
+        );
+        let event = properties_changed_event(&signal(zvariant::Value::from("yes")));
+        assert!(matches!(event, Some(Event::Modified)), "{event:?}");
+    }
+
+    #[tokio::test]
+    async fn environments_are_added_and_removed() {
+        let (_bus, service, backend) = setup().await;
+        let old = find(&backend, "old").await;
+        let mut events = backend.object_manager_events().await.unwrap();
+
+        backend.destroy(&old.path, true, true).await.unwrap();
+        match next(&mut events).await {
+            Event::Removed(path) => assert_eq!(path, old.path),
+            event => panic!("unexpected event: {event:?}"),
+        }
+
+        let mut created = old.clone();
+        created.name = "created".to_string();
+        created.mountpoint = None;
+        service.backend().insert(created);
+        match next(&mut events).await {
+            Event::Added(env) => {
+                assert_eq!(env.path, old.path);
+                assert_eq!(env.name, "created");
+                assert_eq!(env.mountpoint, None);
+            }
+            event => panic!("unexpected event: {event:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn errors_are_classified() {
+        let (_bus, _service, backend) = setup().await;
+        let default = find(&backend, "default").await;
+        let upgrade = find(&backend, "upgrade").await;
+
+        let error = backend.rename(&upgrade.path, "default").await.unwrap_err();
+        assert!(matches!(error, AppError::Backend(_)), "{error:?}");
+
+        let error = backend
+            .destroy(&default.path, false, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, AppError::Backend(_)), "{error:?}");
+
+        let missing =
+            zvariant::OwnedObjectPath::try_from("/ca/kamacite/BootEnvironments/0").unwrap();
+        let error = backend.activate(&missing, true).await.unwrap_err();
+        assert_eq!(error, AppError::NoSuchObject);
+    }
+
+    #[tokio::test]
+    async fn service_restarts_are_reported() {
+        let (bus, service, backend) = setup().await;
+        let mut available = backend.service_events().await.unwrap();
+
+        service.stop().await.unwrap();
+        assert!(!next(&mut available).await);
+        assert_eq!(backend.list().await.unwrap_err(), AppError::ServiceUnknown);
+
+        let _service = MockService::start(&bus, FIXTURE).await.unwrap();
+        assert!(next(&mut available).await);
+        assert_eq!(backend.list().await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn rebooting_goes_through_logind() {
+        let (_bus, service, backend) = setup().await;
+        assert!(backend.can_reboot().await.unwrap());
+
+        let inhibitor = |what: &str, mode: &str| Inhibitor {
+            what: what.to_string(),
+            who: "Updater".to_string(),
+            why: "Installing updates".to_string(),
+            mode: mode.to_string(),
+            uid: 1000,
+            pid: 42,
+        };
+        service.backend().set_inhibitors(vec![
+            inhibitor("shutdown:sleep", "block"),
+            inhibitor("shutdown", "delay"),
+            inhibitor("sleep", "block"),
+        ]);
+        let inhibitors = backend.shutdown_inhibitors().await.unwrap();
+        assert_eq!(inhibitors, [inhibitor("shutdown:sleep", "block")]);
+
+        backend.reboot().await.unwrap();
+        assert_eq!(service.backend().reboots(), 1);
+    }
+
+    #[tokio::test]
+    async fn shutdown_is_blocked_until_the_lock_is_dropped() {
+        let (_bus, service, backend) = setup().await;
+
+        let lock = backend
+            .inhibit_shutdown("Tests", "Deleting old")
+            .await
+            .unwrap();
+        let inhibitors = backend.shutdown_inhibitors().await.unwrap();
+        let [inhibitor] = &inhibitors[..] else {
+            panic!("expected one inhibitor, got {inhibitors:?}");
+        };
+        assert_eq!(
+            (inhibitor.who.as_str(), inhibitor.why.as_str()),
+            ("Tests", "Deleting old")
+        );
+        assert_eq!(service.backend().inhibitions().len(), 1);
+
+        drop(lock);
+        tokio::time::timeout(TIMEOUT, async {
+            while !backend.shutdown_inhibitors().await.unwrap().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("timed out waiting for the lock to be released");
+    }
+
+    #[tokio::test]
+    async fn updates_are_picked_out_of_package_transactions() {
+        let (_bus, service, backend) = setup().await;
+        assert_eq!(backend.update_transaction().await, None);
+
+        // Refreshing the cache, a dry run and a download don't update
+        // anything.
+        service.start_transaction(13, 0).await.unwrap();
+        service.start_transaction(22, 1 << 2).await.unwrap();
+        service.start_transaction(33, 1 << 3).await.unwrap();
+        assert_eq!(backend.update_transaction().await, None);
+
+        let update = service.start_transaction(22, 1 << 1).await.unwrap();
+        let transaction = backend.update_transaction().await.unwrap();
+        assert_eq!(
+            transaction,
+            PackageTransaction {
+                id: update.clone(),
+                kind: UpgradeKind::UpdatePackages
+            }
+        );
+        assert!(transaction
+            .snapshot_description()
+            .ends_with(&format!("({update})")));
+    }
+
+    #[tokio::test]
+    async fn losing_the_bus_is_reported() {
+        let (bus, _service, backend) = setup().await;
+        let closed = backend.closed();
+
+        drop(bus);
+        let error = tokio::time::timeout(TIMEOUT, closed)
+            .await
+            .expect("timed out waiting for the connection to close");
+        assert!(matches!(error, AppError::Disconnected(_)));
+    }
+}
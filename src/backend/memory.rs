@@ -0,0 +1,554 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Boot environments that only exist in memory, for demos and tests.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+use futures_util::future::{self, BoxFuture};
+use futures_util::stream::{self, BoxStream};
+use futures_util::StreamExt;
+use tokio::sync::broadcast;
+use zbus::zvariant;
+
+use super::{Backend, Event, ShutdownLock};
+use crate::error::AppError;
+use crate::login1::Inhibitor;
+use crate::model::{BootEnvironmentChanges, BootEnvironmentObject, Snapshot};
+use crate::packagekit::PackageTransaction;
+
+/// The pool dataset that in-memory boot environments pretend to live under.
+const ROOT_DATASET: &str = "zroot/ROOT";
+
+/// Keeps boot environments in memory and behaves roughly like the real
+/// service, including reporting changes through [`Backend::watch`].
+#[derive(Debug)]
+pub struct MemoryBackend {
+    state: Mutex<State>,
+    events: broadcast::Sender<Event>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    environments: Vec<BootEnvironmentObject>,
+    snapshots: HashMap<zvariant::OwnedObjectPath, Vec<Snapshot>>,
+    inhibitors: Vec<Inhibitor>,
+    /// Every shutdown lock taken, along with whether it is still held.
+    inhibitions: Vec<(Weak<()>, Inhibitor)>,
+    reboots: usize,
+    /// Whether policy forbids the user from rebooting.
+    reboot_forbidden: bool,
+}
+
+impl State {
+    fn environment(
+        &mut self,
+        path: &zvariant::OwnedObjectPath,
+    ) -> Result<&mut BootEnvironmentObject, AppError> {
+        self.environments
+            .iter_mut()
+            .find(|env| &env.path == path)
+            .ok_or(AppError::NoSuchObject)
+    }
+}
+
+impl MemoryBackend {
+    /// Create a backend holding the given boot environments and their
+    /// snapshots.
+    pub fn new(
+        environments: Vec<BootEnvironmentObject>,
+        snapshots: HashMap<zvariant::OwnedObjectPath, Vec<Snapshot>>,
+    ) -> Self {
+        let (events, _) = broadcast::channel(32);
+        MemoryBackend {
+            state: Mutex::new(State {
+                environments,
+                snapshots,
+                ..Default::default()
+            }),
+            events,
+        }
+    }
+
+    /// Create a backend with a few plausible boot environments to play with.
+    pub fn demo() -> Self {
+        let now = chrono::Utc::now().timestamp();
+        let day = 24 * 60 * 60;
+        let mut environments = vec![
+            demo_environment(1, "default", None, now - 90 * day, 8 << 30),
+            demo_environment(
+                2,
+                "pre-upgrade",
+                Some("Before the 24.04 upgrade"),
+                now - 30 * day,
+                2 << 30,
+            ),
+            demo_environment(3, "testing", None, now - 2 * day, 512 << 20),
+        ];
+        environments[0].active = true;
+        environments[0].next_boot = true;
+
+        let mut snapshots = HashMap::new();
+        for (env, offsets) in environments.iter().zip([&[60, 20, 1][..], &[10], &[]]) {
+            let list = offsets
+                .iter()
+                .map(|offset| {
+                    let name = format!(
+                        "{}@{}",
+                        env.name,
+                        chrono::DateTime::from_timestamp(now - offset * day, 0)
+                            .unwrap_or_default()
+                            .format("%Y-%m-%d")
+                    );
+                    Snapshot {
+                        path: format!("{ROOT_DATASET}/{name}"),
+                        name,
+                        description: None,
+                        space: 64 << 20,
+                        created: now - offset * day,
+                    }
+                })
+                .collect();
+            snapshots.insert(env.path.clone(), list);
+        }
+
+        MemoryBackend::new(environments, snapshots)
+    }
+
+    /// Add a boot environment, as if it had been created elsewhere.
+    pub fn insert(&self, env: BootEnvironmentObject) {
+        self.state().environments.push(env.clone());
+        self.emit([Event::Added(env)]);
+    }
+
+    /// Pretend that some programs are blocking shutdown.
+    pub fn set_inhibitors(&self, inhibitors: Vec<Inhibitor>) {
+        self.state().inhibitors = inhibitors;
+    }
+
+    /// Pretend that policy allows or forbids the user to reboot.
+    pub fn set_can_reboot(&self, can_reboot: bool) {
+        self.state().reboot_forbidden = !can_reboot;
+    }
+
+    /// Every shutdown lock taken so far, whether or not it is still held.
+    pub fn inhibitions(&self) -> Vec<Inhibitor> {
+        self.state()
+            .inhibitions
+            .iter()
+            .map(|(_, inhibitor)| inhibitor.clone())
+            .collect()
+    }
+
+    /// How many times the system would have been rebooted.
+    pub fn reboots(&self) -> usize {
+        self.state().reboots
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        // A panic while holding the lock can't leave the state inconsistent
+        // enough to matter for a demo.
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Report changes to anyone watching. It's fine if nobody is.
+    fn emit(&self, events: impl IntoIterator<Item = Event>) {
+        for event in events {
+            let _ = self.events.send(event);
+        }
+    }
+
+    /// Set a boolean flag on exactly one boot environment, clearing it on the
+    /// others, and report the changes.
+    fn set_exclusive(
+        &self,
+        path: &zvariant::OwnedObjectPath,
+        flag: fn(&mut BootEnvironmentObject) -> &mut bool,
+        change: fn(&mut BootEnvironmentChanges) -> &mut Option<bool>,
+    ) -> Result<(), AppError> {
+        let mut state = self.state();
+        state.environment(path)?;
+
+        let mut events = Vec::new();
+        for env in &mut state.environments {
+            let value = &env.path == path;
+            if *flag(env) != value {
+                *flag(env) = value;
+                let mut changes = BootEnvironmentChanges::default();
+                *change(&mut changes) = Some(value);
+                events.push(Event::Changed(env.path.clone(), changes));
+            }
+        }
+        drop(state);
+        self.emit(events);
+        Ok(())
+    }
+}
+
+/// A boot environment for the demo backend.
+fn demo_environment(
+    id: u32,
+    name: &str,
+    description: Option<&str>,
+    created: i64,
+    space: u64,
+) -> BootEnvironmentObject {
+    BootEnvironmentObject {
+        path: zvariant::OwnedObjectPath::try_from(format!("/ca/kamacite/BootEnvironments/{id}"))
+            .expect("demo object paths are valid"),
+        name: name.to_string(),
+        description: description.map(str::to_string),
+        active: false,
+        next_boot: false,
+        boot_once: false,
+        created,
+        mountpoint: None,
+        space,
+        other_properties: Default::default(),
+        undecoded: Default::default(),
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn list(&self) -> BoxFuture<'_, Result<Vec<BootEnvironmentObject>, AppError>> {
+        Box::pin(async move { Ok(self.state().environments.clone()) })
+    }
+
+    fn watch_service(&self) -> BoxStream<'static, bool> {
+        // Memory never goes away.
+        stream::pending().boxed()
+    }
+
+    fn watch(&self) -> BoxStream<'static, Event> {
+        stream::unfold(self.events.subscribe(), |mut rx| async move {
+            match rx.recv().await {
+                Ok(event) => Some((event, rx)),
+                // Whatever we missed, a reload will catch up on.
+                Err(broadcast::error::RecvError::Lagged(_)) => Some((Event::Modified, rx)),
+                Err(broadcast::error::RecvError::Closed) => None,
+            }
+        })
+        .boxed()
+    }
+
+    fn closed(&self) -> BoxFuture<'static, AppError> {
+        // Nor is it ever disconnected.
+        Box::pin(future::pending())
+    }
+
+    fn snapshots<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+    ) -> BoxFuture<'a, Result<Vec<Snapshot>, AppError>> {
+        Box::pin(async move {
+            let mut state = self.state();
+            state.environment(path)?;
+            Ok(state.snapshots.get(path).cloned().unwrap_or_default())
+        })
+    }
+
+    fn activate<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        temporary: bool,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            if temporary {
+                self.set_exclusive(path, |env| &mut env.boot_once, |c| &mut c.boot_once)
+            } else {
+                // Making an environment the default also cancels any one-time
+                // activation.
+                self.set_exclusive(path, |env| &mut env.next_boot, |c| &mut c.next_boot)?;
+                let mut state = self.state();
+                let mut events = Vec::new();
+                for env in state.environments.iter_mut().filter(|env| env.boot_once) {
+                    env.boot_once = false;
+                    events.push(Event::Changed(
+                        env.path.clone(),
+                        BootEnvironmentChanges {
+                            boot_once: Some(false),
+                            ..Default::default()
+                        },
+                    ));
+                }
+                drop(state);
+                self.emit(events);
+                Ok(())
+            }
+        })
+    }
+
+    fn snapshot<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        name: &'a str,
+        description: &'a str,
+    ) -> BoxFuture<'a, Result<String, AppError>> {
+        Box::pin(async move {
+            let mut state = self.state();
+            let full_name = format!("{}@{name}", state.environment(path)?.name);
+            let snapshots = state.snapshots.entry(path.clone()).or_default();
+            if snapshots.iter().any(|s| s.name == full_name) {
+                return Err(AppError::Backend(format!(
+                    "snapshot '{full_name}' already exists"
+                )));
+            }
+            snapshots.push(Snapshot {
+                name: full_name.clone(),
+                path: format!("{ROOT_DATASET}/{full_name}"),
+                description: (!description.is_empty()).then(|| description.to_string()),
+                space: 0,
+                created: chrono::Utc::now().timestamp(),
+            });
+            Ok(full_name)
+        })
+    }
+
+    fn rollback<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        snapshot: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let mut state = self.state();
+            state.environment(path)?;
+            let snapshots = state.snapshots.entry(path.clone()).or_default();
+            let Some(created) = snapshots
+                .iter()
+                .find(|s| s.name == snapshot)
+                .map(|s| s.created)
+            else {
+                return Err(AppError::Backend(format!(
+                    "snapshot '{snapshot}' does not exist"
+                )));
+            };
+            // Like the applet's warning, go by creation time. Snapshots taken
+            // in the same second can't be told apart, so assume the worst.
+            snapshots.retain(|s| s.created < created || s.name == snapshot);
+            Ok(())
+        })
+    }
+
+    fn destroy_snapshot<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        snapshot: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let mut state = self.state();
+            state.environment(path)?;
+            let snapshots = state.snapshots.entry(path.clone()).or_default();
+            let before = snapshots.len();
+            snapshots.retain(|s| s.name != snapshot);
+            if snapshots.len() == before {
+                return Err(AppError::Backend(format!(
+                    "snapshot '{snapshot}' does not exist"
+                )));
+            }
+            Ok(())
+        })
+    }
+
+    fn rename<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        new_name: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let mut state = self.state();
+            if state.environments.iter().any(|env| env.name == new_name) {
+                return Err(AppError::Backend(format!(
+                    "boot environment '{new_name}' already exists"
+                )));
+            }
+            let env = state.environment(path)?;
+            let old_prefix = format!("{}@", env.name);
+            env.name = new_name.to_string();
+
+            // Snapshots are named after their boot environment.
+            for snapshot in state.snapshots.entry(path.clone()).or_default() {
+                if let Some(rest) = snapshot.name.strip_prefix(&old_prefix) {
+                    snapshot.name = format!("{new_name}@{rest}");
+                    snapshot.path = format!("{ROOT_DATASET}/{}", snapshot.name);
+                }
+            }
+            drop(state);
+
+            self.emit([Event::Changed(
+                path.clone(),
+                BootEnvironmentChanges {
+                    name: Some(new_name.to_string()),
+                    ..Default::default()
+                },
+            )]);
+            Ok(())
+        })
+    }
+
+    fn describe<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        description: &'a str,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let description = (!description.is_empty()).then(|| description.to_string());
+            self.state().environment(path)?.description = description.clone();
+            self.emit([Event::Changed(
+                path.clone(),
+                BootEnvironmentChanges {
+                    description: Some(description),
+                    ..Default::default()
+                },
+            )]);
+            Ok(())
+        })
+    }
+
+    fn destroy<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        force_unmount: bool,
+        snapshots: bool,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let mut state = self.state();
+            let env = state.environment(path)?;
+            if env.active || env.next_boot || env.boot_once {
+                return Err(AppError::Backend(format!(
+                    "boot environment '{}' is in use",
+                    env.name
+                )));
+            }
+            if env.mountpoint.is_some() && !force_unmount {
+                return Err(AppError::Backend(format!(
+                    "boot environment '{}' is mounted",
+                    env.name
+                )));
+            }
+            if !snapshots && state.snapshots.get(path).is_some_and(|s| !s.is_empty()) {
+                return Err(AppError::Backend(
+                    "boot environment has snapshots".to_string(),
+                ));
+            }
+            state.environments.retain(|env| &env.path != path);
+            state.snapshots.remove(path);
+            drop(state);
+
+            self.emit([Event::Removed(path.clone())]);
+            Ok(())
+        })
+    }
+
+    fn mount<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        mountpoint: &'a str,
+        _read_only: bool,
+    ) -> BoxFuture<'a, Result<(), AppError>> {
+        Box::pin(async move {
+            let mut state = self.state();
+            let env = state.environment(path)?;
+            if env.mountpoint.is_some() {
+                return Err(AppError::Backend(format!(
+                    "boot environment '{}' is already mounted",
+                    env.name
+                )));
+            }
+            env.mountpoint = Some(mountpoint.to_string());
+            drop(state);
+
+            self.emit([Event::Changed(
+                path.clone(),
+                BootEnvironmentChanges {
+                    mountpoint: Some(Some(mountpoint.to_string())),
+                    ..Default::default()
+                },
+            )]);
+            Ok(())
+        })
+    }
+
+    fn unmount<'a>(
+        &'a self,
+        path: &'a zvariant::OwnedObjectPath,
+        _force: bool,
+    ) -> BoxFuture<'a, Result<String, AppError>> {
+        Box::pin(async move {
+            let mut state = self.state();
+            let env = state.environment(path)?;
+            let Some(mountpoint) = env.mountpoint.take() else {
+                return Err(AppError::Backend(format!(
+                    "boot environment '{}' is not mounted",
+                    env.name
+                )));
+            };
+            drop(state);
+
+            self.emit([Event::Changed(
+                path.clone(),
+                BootEnvironmentChanges {
+                    mountpoint: Some(None),
+                    ..Default::default()
+                },
+            )]);
+            Ok(mountpoint)
+        })
+    }
+
+    fn can_reboot(&self) -> BoxFuture<'_, Result<bool, AppError>> {
+        let can_reboot = !self.state().reboot_forbidden;
+        Box::pin(async move { Ok(can_reboot) })
+    }
+
+    fn shutdown_inhibitors(&self) -> BoxFuture<'_, Result<Vec<Inhibitor>, AppError>> {
+        Box::pin(async move {
+            let state = self.state();
+            let held = state
+                .inhibitions
+                .iter()
+                .filter(|(lock, _)| lock.strong_count() > 0)
+                .map(|(_, inhibitor)| inhibitor);
+            Ok(state.inhibitors.iter().chain(held).cloned().collect())
+        })
+    }
+
+    fn reboot(&self) -> BoxFuture<'_, Result<(), AppError>> {
+        Box::pin(async move {
+            // Rebooting for real would make for a short demo.
+            tracing::info!("Pretending to reboot");
+            self.state().reboots += 1;
+            Ok(())
+        })
+    }
+
+    fn inhibit_shutdown<'a>(
+        &'a self,
+        who: &'a str,
+        why: &'a str,
+    ) -> BoxFuture<'a, Result<ShutdownLock, AppError>> {
+        Box::pin(async move {
+            let lock = Arc::new(());
+            let inhibitor = Inhibitor {
+                what: "shutdown:sleep".to_string(),
+                who: who.to_string(),
+                why: why.to_string(),
+                mode: "block".to_string(),
+                uid: 0,
+                pid: std::process::id(),
+            };
+            self.state()
+                .inhibitions
+                .push((Arc::downgrade(&lock), inhibitor));
+            Ok(ShutdownLock::new(lock))
+        })
+    }
+
+    fn update_transaction(&self) -> BoxFuture<'_, Option<PackageTransaction>> {
+        // There is no package manager to ask.
+        Box::pin(future::ready(None))
+    }
+}
@@ -0,0 +1,46 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Settings for the applet, stored with `cosmic-config`.
+
+use cosmic::cosmic_config::{self, cosmic_config_derive::CosmicConfigEntry, CosmicConfigEntry};
+
+/// Settings for the applet, shared by all of its instances.
+#[derive(Debug, Clone, CosmicConfigEntry, Eq, PartialEq)]
+#[version = 1]
+pub struct Config {
+    /// Whether to notify the user when the boot environment that will start
+    /// next is changed elsewhere.
+    pub notify_boot_changes: bool,
+    /// Whether to notify the user when boot environments are created or
+    /// deleted elsewhere.
+    pub notify_environment_changes: bool,
+    /// Whether the package manager hooks snapshot the running boot
+    /// environment before updating it. The hooks run as root for the whole
+    /// system, so this is only read from the system-wide settings.
+    pub snapshot_before_upgrades: bool,
+}
+
+/// What the applet remembers between sessions, kept in `cosmic-config`'s
+/// state rather than with the settings.
+#[derive(Debug, Clone, Default, CosmicConfigEntry, Eq, PartialEq)]
+#[version = 1]
+pub struct State {
+    /// The object path of the boot environment last seen scheduled to boot
+    /// once, by the applet or anything else, until that has played out. Only
+    /// that environment is offered to be kept after booting into it.
+    pub boot_once: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            notify_boot_changes: true,
+            notify_environment_changes: true,
+            snapshot_before_upgrades: true,
+        }
+    }
+}
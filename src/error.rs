@@ -0,0 +1,113 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Sorts D-Bus failures into errors that can be explained to the user.
+
+use crate::fl;
+
+/// Prefix of the error names used by the boot environment service itself.
+const BACKEND_ERROR_PREFIX: &str = "ca.kamacite.";
+
+/// An error from the boot environment service or the bus, classified by what
+/// the user can do about it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AppError {
+    /// The user is not authorized to perform the operation.
+    AccessDenied,
+    /// The boot environment no longer exists.
+    NoSuchObject,
+    /// The boot environment service is not running.
+    ServiceUnknown,
+    /// The service did not reply in time.
+    Timeout,
+    /// The system bus could not be reached.
+    Disconnected(String),
+    /// The service reported a failure of its own, e.g. from ZFS.
+    Backend(String),
+    /// Anything else.
+    Other(String),
+}
+
+impl AppError {
+    /// A localized explanation of this error.
+    pub fn message(&self) -> String {
+        match self {
+            AppError::AccessDenied => fl!("error-access-denied"),
+            AppError::NoSuchObject => fl!("error-no-such-object"),
+            AppError::ServiceUnknown => fl!("error-service-unknown"),
+            AppError::Timeout => fl!("error-timeout"),
+            AppError::Disconnected(details) => {
+                fl!("error-disconnected", details = details.clone())
+            }
+            AppError::Backend(details) => fl!("error-backend", details = details.clone()),
+            AppError::Other(details) => fl!("error-other", details = details.clone()),
+        }
+    }
+
+    /// Classify a D-Bus error name, falling back to `details` (or the name
+    /// itself) for errors we don't recognise.
+    fn from_error_name(name: &str, details: Option<&str>) -> Self {
+        match name {
+            "org.freedesktop.DBus.Error.AccessDenied"
+            | "org.freedesktop.DBus.Error.AuthFailed"
+            | "org.freedesktop.DBus.Error.InteractiveAuthorizationRequired"
+            | "org.freedesktop.PolicyKit1.Error.NotAuthorized"
+            | "org.freedesktop.PolicyKit1.Error.Cancelled" => AppError::AccessDenied,
+            "org.freedesktop.DBus.Error.UnknownObject"
+            | "org.freedesktop.DBus.Error.UnknownInterface" => AppError::NoSuchObject,
+            "org.freedesktop.DBus.Error.ServiceUnknown"
+            | "org.freedesktop.DBus.Error.NameHasNoOwner" => AppError::ServiceUnknown,
+            "org.freedesktop.DBus.Error.Timeout"
+            | "org.freedesktop.DBus.Error.TimedOut"
+            | "org.freedesktop.DBus.Error.NoReply" => AppError::Timeout,
+            _ => {
+                let details = details.unwrap_or(name).to_string();
+                if name.starts_with(BACKEND_ERROR_PREFIX) {
+                    AppError::Backend(details)
+                } else {
+                    AppError::Other(details)
+                }
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<&zbus::fdo::Error> for AppError {
+    fn from(error: &zbus::fdo::Error) -> Self {
+        use zbus::DBusError;
+
+        AppError::from_error_name(error.name().as_str(), error.description())
+    }
+}
+
+impl From<&zbus::Error> for AppError {
+    fn from(error: &zbus::Error) -> Self {
+        match error {
+            zbus::Error::MethodError(name, details, _) => {
+                AppError::from_error_name(name.as_str(), details.as_deref())
+            }
+            zbus::Error::FDO(e) => AppError::from(&**e),
+            zbus::Error::Address(_) | zbus::Error::Handshake(_) | zbus::Error::InputOutput(_) => {
+                AppError::Disconnected(error.to_string())
+            }
+            _ => AppError::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<zbus::Error> for AppError {
+    fn from(error: zbus::Error) -> Self {
+        AppError::from(&error)
+    }
+}
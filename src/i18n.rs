@@ -6,23 +6,53 @@
 
 //! Provides localization support for this crate.
 
-use std::sync::LazyLock;
+use std::sync::{LazyLock, OnceLock};
 
 use i18n_embed::{
     fluent::{fluent_language_loader, FluentLanguageLoader},
     unic_langid::LanguageIdentifier,
     DefaultLocalizer, LanguageLoader, Localizer,
 };
+use icu_decimal::input::Decimal;
+use icu_decimal::options::DecimalFormatterOptions;
+use icu_decimal::DecimalFormatter;
+use icu_locale_core::Locale;
 use rust_embed::RustEmbed;
 
+/// Formats numbers for the user's preferred language, which needn't be one
+/// the applet is translated into. Fluent's `NUMBER()` can't do this, as it
+/// doesn't localize digits or separators.
+static DECIMAL_FORMATTER: OnceLock<Option<DecimalFormatter>> = OnceLock::new();
+
 /// Applies the requested language(s) to requested translations from the `fl!()` macro.
 pub fn init(requested_languages: &[LanguageIdentifier]) {
     if let Err(why) = localizer().select(requested_languages) {
         eprintln!("error while loading fluent localizations: {why}");
     }
+    let _ = DECIMAL_FORMATTER.set(decimal_formatter(requested_languages));
+}
+
+/// Format a number for the user's preferred language.
+pub fn format_decimal(value: &Decimal) -> String {
+    match DECIMAL_FORMATTER.get_or_init(|| decimal_formatter(&[])) {
+        Some(formatter) => formatter.format(value).to_string(),
+        None => value.to_string(),
+    }
+}
+
+/// A number formatter for the first of `languages`, or for no language in
+/// particular if there are none.
+fn decimal_formatter(languages: &[LanguageIdentifier]) -> Option<DecimalFormatter> {
+    let locale = languages
+        .first()
+        .and_then(|language| Locale::try_from_str(&language.to_string()).ok())
+        .unwrap_or(Locale::UNKNOWN);
+    DecimalFormatter::try_new((&locale).into(), DecimalFormatterOptions::default())
+        .map_err(|e| eprintln!("error while loading number formats: {e}"))
+        .ok()
 }
 
-// Get the `Localizer` to be used for localizing this library.
+/// Get the `Localizer` to be used for localizing this library.
 #[must_use]
 pub fn localizer() -> Box<dyn Localizer> {
     Box::from(DefaultLocalizer::new(&*LANGUAGE_LOADER, &Localizations))
@@ -32,6 +62,7 @@ pub fn localizer() -> Box<dyn Localizer> {
 #[folder = "i18n/"]
 struct Localizations;
 
+/// Loads the translations used by the `fl!()` macro.
 pub static LANGUAGE_LOADER: LazyLock<FluentLanguageLoader> = LazyLock::new(|| {
     let loader: FluentLanguageLoader = fluent_language_loader!();
 
@@ -53,3 +84,22 @@ macro_rules! fl {
         i18n_embed_fl::fl!($crate::i18n::LANGUAGE_LOADER, $message_id, $($args), *)
     }};
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_follow_the_preferred_language() {
+        let mut value = Decimal::from(10_245);
+        value.multiply_pow10(-1);
+        let format = |language: &str| {
+            decimal_formatter(&[language.parse().unwrap()])
+                .unwrap()
+                .format(&value)
+                .to_string()
+        };
+        assert_eq!(format("en-US"), "1,024.5");
+        assert_eq!(format("de-DE"), "1.024,5");
+    }
+}
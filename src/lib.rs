@@ -0,0 +1,42 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A client for the [`beadm`](https://github.com/kamacite-linux/beadm) boot
+//! environment service, and the COSMIC applet built on it.
+//!
+//! Boot environments are described by [`model::BootEnvironmentObject`] and
+//! reached through a [`backend::Backend`]: normally
+//! [`backend::DBusBackend`], which talks to the service on the system bus,
+//! or [`backend::MemoryBackend`] for demos and tests. The generated D-Bus
+//! proxy is available as [`dbus::BootEnvironmentProxy`] for anything the
+//! backend doesn't cover.
+//!
+//! ```no_run
+//! use cosmic_applet_boot_environment::backend::{Backend, DBusBackend};
+//!
+//! # async fn example() -> Result<(), cosmic_applet_boot_environment::error::AppError> {
+//! let backend = DBusBackend::system().await?;
+//! for env in backend.list().await? {
+//!     println!("{}{}", env.label(), if env.active { " (running)" } else { "" });
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod app;
+pub mod backend;
+pub mod config;
+pub mod dbus;
+pub mod error;
+pub mod i18n;
+pub mod login1;
+#[cfg(test)]
+mod mock_service;
+pub mod model;
+pub mod notifications;
+pub mod notifier;
+pub mod packagekit;
+pub mod update_hook;
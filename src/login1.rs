@@ -0,0 +1,79 @@
+//! # D-Bus interface proxy for: `org.freedesktop.login1.Manager`
+//!
+//! Adapted from code generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection data.
+//! Source: `Interface '/org/freedesktop/login1' from service 'org.freedesktop.login1' on system bus`.
+//!
+//! Only the parts of the interface that the applet uses are included.
+//!
+//! More information can be found in the [Writing a client proxy] section of the zbus
+//! documentation.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+use zbus::proxy;
+
+#[proxy(
+    interface = "org.freedesktop.login1.Manager",
+    default_service = "org.freedesktop.login1",
+    default_path = "/org/freedesktop/login1"
+)]
+pub trait Manager {
+    /// CanReboot method
+    fn can_reboot(&self) -> zbus::Result<String>;
+
+    /// Inhibit method
+    fn inhibit(
+        &self,
+        what: &str,
+        who: &str,
+        why: &str,
+        mode: &str,
+    ) -> zbus::Result<zbus::zvariant::OwnedFd>;
+
+    /// ListInhibitors method
+    #[allow(clippy::type_complexity)]
+    fn list_inhibitors(&self) -> zbus::Result<Vec<(String, String, String, String, u32, u32)>>;
+
+    /// Reboot method
+    fn reboot(&self, interactive: bool) -> zbus::Result<()>;
+}
+
+/// A program that has asked logind to hold off shutting down, as returned by
+/// `ListInhibitors`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inhibitor {
+    /// What is inhibited, as a colon-separated list such as `shutdown:sleep`.
+    pub what: String,
+    /// A human-readable name for the program.
+    pub who: String,
+    /// A human-readable reason for the inhibition.
+    pub why: String,
+    /// Either `block` or `delay`.
+    pub mode: String,
+    /// The user that took the inhibitor.
+    pub uid: u32,
+    /// The process that took the inhibitor.
+    pub pid: u32,
+}
+
+impl From<(String, String, String, String, u32, u32)> for Inhibitor {
+    /// Decode the raw `(what, who, why, mode, uid, pid)` tuple used on the
+    /// bus.
+    fn from((what, who, why, mode, uid, pid): (String, String, String, String, u32, u32)) -> Self {
+        Inhibitor {
+            what,
+            who,
+            why,
+            mode,
+            uid,
+            pid,
+        }
+    }
+}
+
+impl Inhibitor {
+    /// Whether this prevents shutting down or rebooting, rather than merely
+    /// delaying it or inhibiting something else.
+    pub fn blocks_shutdown(&self) -> bool {
+        self.mode == "block" && self.what.split(':').any(|what| what == "shutdown")
+    }
+}
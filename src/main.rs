@@ -4,9 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-mod app;
-mod dbus;
-mod i18n;
+use cosmic_applet_boot_environment::{app, i18n, update_hook};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -22,6 +20,12 @@ fn main() -> cosmic::iced::Result {
     // Enable localizations to be applied.
     i18n::init(&requested_languages);
 
+    // Package managers run the same binary to snapshot before updating.
+    if std::env::args().nth(1).as_deref() == Some(update_hook::ARG) {
+        update_hook::run();
+        return Ok(());
+    }
+
     // Starts the applet's event loop with `()` as the application's flags.
     cosmic::applet::run::<app::AppModel>(())
 }
@@ -0,0 +1,611 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! A stand-in for the `beadm` D-Bus service, for testing the applet end to end
+//! without ZFS.
+//!
+//! The mock serves the same objects and emits the same signals as the real
+//! service, but keeps its boot environments in a [`MemoryBackend`] seeded
+//! from a JSON fixture. It runs on a private `dbus-daemon` started by
+//! [`TestBus`], alongside just enough of logind to pretend to reboot and of
+//! PackageKit to pretend to update.
+
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::path::Path;
+use std::sync::Arc;
+
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use zbus::object_server::ObjectServer;
+use zbus::zvariant;
+
+use crate::backend::{Backend, Event, MemoryBackend};
+use crate::error::AppError;
+use crate::login1::Inhibitor;
+use crate::model::{BootEnvironmentChanges, BootEnvironmentObject, Snapshot};
+
+/// The well-known bus name claimed by the mock.
+const SERVICE_NAME: &str = "ca.kamacite.BootEnvironments1";
+
+/// Where the mock serves its object manager.
+const OBJECT_MANAGER_PATH: &str = "/ca/kamacite/BootEnvironments";
+
+/// The well-known bus name of logind, also claimed by the mock.
+const LOGIN1_NAME: &str = "org.freedesktop.login1";
+
+/// Where logind serves its manager.
+const LOGIN1_PATH: &str = "/org/freedesktop/login1";
+
+/// The well-known bus name of PackageKit, also claimed by the mock.
+const PACKAGEKIT_NAME: &str = "org.freedesktop.PackageKit";
+
+/// Where PackageKit serves its transaction list.
+const PACKAGEKIT_PATH: &str = "/org/freedesktop/PackageKit";
+
+/// The pool dataset that mock boot environments live under.
+const ROOT_DATASET: &str = "zroot/ROOT";
+
+/// The fixture used by default, relative to the crate root.
+pub const FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/boot-environments.json"
+);
+
+/// A private message bus, torn down when dropped.
+pub struct TestBus {
+    daemon: std::process::Child,
+    address: String,
+}
+
+impl TestBus {
+    /// Start a new `dbus-daemon` with the session bus configuration.
+    pub fn start() -> std::io::Result<Self> {
+        let mut daemon = std::process::Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(std::process::Stdio::piped())
+            .spawn()?;
+
+        // The daemon prints its address once it is ready for connections.
+        let mut address = String::new();
+        if let Some(stdout) = daemon.stdout.take() {
+            std::io::BufReader::new(stdout).read_line(&mut address)?;
+        }
+        let address = address.trim().to_string();
+        if address.is_empty() {
+            let _ = daemon.kill();
+            return Err(std::io::Error::other(
+                "dbus-daemon did not report an address",
+            ));
+        }
+
+        Ok(TestBus { daemon, address })
+    }
+
+    /// Open a new connection to this bus.
+    pub async fn connect(&self) -> zbus::Result<zbus::Connection> {
+        zbus::connection::Builder::address(self.address.as_str())?
+            .build()
+            .await
+    }
+}
+
+impl Drop for TestBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// The mock service, running until dropped or stopped.
+pub struct MockService {
+    conn: zbus::Connection,
+    backend: Arc<MemoryBackend>,
+    forwarder: tokio::task::JoinHandle<()>,
+}
+
+impl MockService {
+    /// Start serving the boot environments in a fixture on a bus.
+    pub async fn start(bus: &TestBus, fixture: impl AsRef<Path>) -> zbus::Result<Self> {
+        let backend = Arc::new(load_fixture(fixture.as_ref())?);
+        // Subscribe before anything can change.
+        let events = backend.watch();
+
+        let environments = backend
+            .list()
+            .await
+            .map_err(|e| zbus::Error::Failure(e.to_string()))?;
+        let mut builder = zbus::connection::Builder::address(bus.address.as_str())?
+            .serve_at(OBJECT_MANAGER_PATH, zbus::fdo::ObjectManager)?
+            .serve_at(LOGIN1_PATH, MockLogind::new(backend.clone()))?
+            .serve_at(PACKAGEKIT_PATH, MockPackageKit::default())?;
+        for env in environments {
+            builder = builder.serve_at(
+                env.path.clone(),
+                MockBootEnvironment::new(env.path, backend.clone()),
+            )?;
+        }
+        // Only claim the name once the objects are in place, as a client may
+        // start loading them as soon as it appears.
+        let conn = builder
+            .name(LOGIN1_NAME)?
+            .name(PACKAGEKIT_NAME)?
+            .name(SERVICE_NAME)?
+            .build()
+            .await?;
+
+        let forwarder = tokio::spawn(forward_events(conn.clone(), backend.clone(), events));
+        Ok(MockService {
+            conn,
+            backend,
+            forwarder,
+        })
+    }
+
+    /// The boot environments behind the mock, for changing them behind the
+    /// client's back.
+    pub fn backend(&self) -> &MemoryBackend {
+        &self.backend
+    }
+
+    /// Stop the service, releasing its names on the bus.
+    pub async fn stop(self) -> zbus::Result<()> {
+        self.forwarder.abort();
+        self.conn.release_name(SERVICE_NAME).await?;
+        self.conn.release_name(LOGIN1_NAME).await?;
+        self.conn.release_name(PACKAGEKIT_NAME).await?;
+        Ok(())
+    }
+
+    /// Start a PackageKit transaction with the given role and flags, as if a
+    /// client had asked PackageKit to do something, returning its ID.
+    pub async fn start_transaction(&self, role: u32, flags: u64) -> zbus::Result<String> {
+        let server = self.conn.object_server();
+        let packagekit = server
+            .interface::<_, MockPackageKit>(PACKAGEKIT_PATH)
+            .await?;
+        let id = {
+            let mut packagekit = packagekit.get_mut().await;
+            packagekit.started += 1;
+            let id = format!("/{}_mock", packagekit.started);
+            packagekit.transactions.push(id.clone());
+            id
+        };
+        server
+            .at(id.as_str(), MockTransaction { role, flags })
+            .await?;
+        Ok(id)
+    }
+}
+
+impl Drop for MockService {
+    fn drop(&mut self) {
+        self.forwarder.abort();
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct Fixture {
+    environments: Vec<FixtureEnvironment>,
+}
+
+#[derive(serde::Deserialize)]
+struct FixtureEnvironment {
+    guid: u64,
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    active: bool,
+    #[serde(default)]
+    next_boot: bool,
+    #[serde(default)]
+    boot_once: bool,
+    created: i64,
+    #[serde(default)]
+    mountpoint: Option<String>,
+    #[serde(default)]
+    space: u64,
+    #[serde(default)]
+    snapshots: Vec<FixtureSnapshot>,
+}
+
+#[derive(serde::Deserialize)]
+struct FixtureSnapshot {
+    /// The name of the snapshot, without the boot environment.
+    name: String,
+    #[serde(default)]
+    description: Option<String>,
+    created: i64,
+    #[serde(default)]
+    space: u64,
+}
+
+/// Read boot environments and their snapshots from a JSON fixture.
+fn load_fixture(path: &Path) -> zbus::Result<MemoryBackend> {
+    let contents = std::fs::read_to_string(path)?;
+    let fixture: Fixture =
+        serde_json::from_str(&contents).map_err(|e| zbus::Error::Failure(e.to_string()))?;
+
+    let mut environments = Vec::new();
+    let mut snapshots = HashMap::new();
+    for env in fixture.environments {
+        let path = zvariant::OwnedObjectPath::try_from(format!(
+            "{OBJECT_MANAGER_PATH}/{:016x}",
+            env.guid
+        ))?;
+        let env_snapshots = env
+            .snapshots
+            .into_iter()
+            .map(|snapshot| {
+                let name = format!("{}@{}", env.name, snapshot.name);
+                Snapshot {
+                    path: format!("{ROOT_DATASET}/{name}"),
+                    name,
+                    description: snapshot.description,
+                    space: snapshot.space,
+                    created: snapshot.created,
+                }
+            })
+            .collect();
+        snapshots.insert(path.clone(), env_snapshots);
+        environments.push(BootEnvironmentObject {
+            path,
+            name: env.name,
+            description: env.description,
+            active: env.active,
+            next_boot: env.next_boot,
+            boot_once: env.boot_once,
+            created: env.created,
+            mountpoint: env.mountpoint,
+            space: env.space,
+            other_properties: Default::default(),
+            undecoded: Default::default(),
+        });
+    }
+
+    Ok(MemoryBackend::new(environments, snapshots))
+}
+
+/// Mirror changes to the boot environments onto the bus: objects come and go
+/// through the object manager, and property changes are signalled.
+async fn forward_events(
+    conn: zbus::Connection,
+    backend: Arc<MemoryBackend>,
+    mut events: BoxStream<'static, Event>,
+) {
+    let server = conn.object_server();
+    while let Some(event) = events.next().await {
+        let result = match event {
+            Event::Added(env) => server
+                .at(
+                    env.path.clone(),
+                    MockBootEnvironment::new(env.path, backend.clone()),
+                )
+                .await
+                .map(drop),
+            Event::Removed(path) => server
+                .remove::<MockBootEnvironment, _>(&path)
+                .await
+                .map(drop),
+            Event::Changed(path, changes) => emit_changes(server, &path, &changes).await,
+            Event::Modified => Ok(()),
+        };
+        if let Err(e) = result {
+            tracing::warn!(error = ?e, "Mock service failed to signal a change");
+        }
+    }
+}
+
+/// Emit `PropertiesChanged` for each changed property of a boot environment.
+async fn emit_changes(
+    server: &ObjectServer,
+    path: &zvariant::OwnedObjectPath,
+    changes: &BootEnvironmentChanges,
+) -> zbus::Result<()> {
+    let iface_ref = server
+        .interface::<_, MockBootEnvironment>(path.as_ref())
+        .await?;
+    let emitter = iface_ref.signal_emitter();
+    let iface = iface_ref.get().await;
+
+    if changes.name.is_some() {
+        iface.name_changed(emitter).await?;
+        iface.root_changed(emitter).await?;
+    }
+    if changes.description.is_some() {
+        iface.description_changed(emitter).await?;
+    }
+    if changes.active.is_some() {
+        iface.active_changed(emitter).await?;
+    }
+    if changes.next_boot.is_some() {
+        iface.next_boot_changed(emitter).await?;
+    }
+    if changes.boot_once.is_some() {
+        iface.boot_once_changed(emitter).await?;
+    }
+    if changes.created.is_some() {
+        iface.created_changed(emitter).await?;
+    }
+    if changes.mountpoint.is_some() {
+        iface.mountpoint_changed(emitter).await?;
+    }
+    if changes.space.is_some() {
+        iface.space_changed(emitter).await?;
+    }
+    Ok(())
+}
+
+/// Errors returned by the mock, named like those of the real service.
+#[derive(Debug, zbus::DBusError)]
+#[zbus(prefix = "ca.kamacite.BootEnvironments1.Error")]
+enum MockError {
+    #[zbus(error)]
+    ZBus(zbus::Error),
+    /// The operation was refused, e.g. because of a name collision.
+    Failed(String),
+}
+
+impl From<AppError> for MockError {
+    fn from(error: AppError) -> Self {
+        match error {
+            AppError::NoSuchObject => MockError::ZBus(
+                zbus::fdo::Error::UnknownObject("no such boot environment".to_string()).into(),
+            ),
+            AppError::Backend(details) | AppError::Other(details) => MockError::Failed(details),
+            error => MockError::Failed(format!("{error:?}")),
+        }
+    }
+}
+
+/// A boot environment served by the mock.
+struct MockBootEnvironment {
+    path: zvariant::OwnedObjectPath,
+    backend: Arc<MemoryBackend>,
+}
+
+impl MockBootEnvironment {
+    fn new(path: zvariant::OwnedObjectPath, backend: Arc<MemoryBackend>) -> Self {
+        MockBootEnvironment { path, backend }
+    }
+
+    /// The current state of this boot environment.
+    async fn get(&self) -> zbus::fdo::Result<BootEnvironmentObject> {
+        self.backend
+            .list()
+            .await
+            .ok()
+            .and_then(|envs| envs.into_iter().find(|env| env.path == self.path))
+            .ok_or_else(|| zbus::fdo::Error::UnknownObject(self.path.to_string()))
+    }
+}
+
+#[zbus::interface(name = "ca.kamacite.BootEnvironment")]
+impl MockBootEnvironment {
+    async fn activate(&self, temporary: bool) -> Result<(), MockError> {
+        Ok(self.backend.activate(&self.path, temporary).await?)
+    }
+
+    async fn describe(&self, description: &str) -> Result<(), MockError> {
+        Ok(self.backend.describe(&self.path, description).await?)
+    }
+
+    async fn destroy(&self, force_unmount: bool, snapshots: bool) -> Result<(), MockError> {
+        Ok(self
+            .backend
+            .destroy(&self.path, force_unmount, snapshots)
+            .await?)
+    }
+
+    async fn destroy_snapshot(&self, snapshot: &str) -> Result<(), MockError> {
+        Ok(self.backend.destroy_snapshot(&self.path, snapshot).await?)
+    }
+
+    async fn get_snapshots(&self) -> Result<Vec<(String, String, String, u64, i64)>, MockError> {
+        let snapshots = self.backend.snapshots(&self.path).await?;
+        Ok(snapshots
+            .into_iter()
+            .map(|s| {
+                (
+                    s.name,
+                    s.path,
+                    s.description.unwrap_or_default(),
+                    s.space,
+                    s.created,
+                )
+            })
+            .collect())
+    }
+
+    async fn mount(&self, mountpoint: &str, read_only: bool) -> Result<(), MockError> {
+        Ok(self
+            .backend
+            .mount(&self.path, mountpoint, read_only)
+            .await?)
+    }
+
+    async fn rename(&self, new_name: &str) -> Result<(), MockError> {
+        Ok(self.backend.rename(&self.path, new_name).await?)
+    }
+
+    async fn rollback(&self, snapshot: &str) -> Result<(), MockError> {
+        Ok(self.backend.rollback(&self.path, snapshot).await?)
+    }
+
+    async fn snapshot(&self, snapshot_name: &str, description: &str) -> Result<String, MockError> {
+        Ok(self
+            .backend
+            .snapshot(&self.path, snapshot_name, description)
+            .await?)
+    }
+
+    async fn unmount(&self, force: bool) -> Result<String, MockError> {
+        Ok(self.backend.unmount(&self.path, force).await?)
+    }
+
+    #[zbus(property)]
+    async fn active(&self) -> zbus::fdo::Result<bool> {
+        Ok(self.get().await?.active)
+    }
+
+    #[zbus(property)]
+    async fn boot_once(&self) -> zbus::fdo::Result<bool> {
+        Ok(self.get().await?.boot_once)
+    }
+
+    #[zbus(property)]
+    async fn created(&self) -> zbus::fdo::Result<i64> {
+        Ok(self.get().await?.created)
+    }
+
+    #[zbus(property)]
+    async fn description(&self) -> zbus::fdo::Result<String> {
+        Ok(self.get().await?.description.unwrap_or_default())
+    }
+
+    #[zbus(property)]
+    async fn guid(&self) -> u64 {
+        // Object paths are named after the GUID.
+        self.path
+            .rsplit('/')
+            .next()
+            .and_then(|guid| u64::from_str_radix(guid, 16).ok())
+            .unwrap_or_default()
+    }
+
+    #[zbus(property)]
+    async fn mountpoint(&self) -> zbus::fdo::Result<String> {
+        Ok(self
+            .get()
+            .await?
+            .mountpoint
+            .unwrap_or_else(|| "-".to_string()))
+    }
+
+    #[zbus(property)]
+    async fn name(&self) -> zbus::fdo::Result<String> {
+        Ok(self.get().await?.name)
+    }
+
+    #[zbus(property)]
+    async fn next_boot(&self) -> zbus::fdo::Result<bool> {
+        Ok(self.get().await?.next_boot)
+    }
+
+    #[zbus(property)]
+    async fn root(&self) -> zbus::fdo::Result<String> {
+        Ok(format!("{ROOT_DATASET}/{}", self.get().await?.name))
+    }
+
+    #[zbus(property)]
+    async fn space(&self) -> zbus::fdo::Result<u64> {
+        Ok(self.get().await?.space)
+    }
+}
+
+/// Enough of PackageKit to list the transactions in progress.
+#[derive(Default)]
+struct MockPackageKit {
+    /// How many transactions have been started, for numbering them.
+    started: u32,
+    /// The IDs of the transactions in progress.
+    transactions: Vec<String>,
+}
+
+#[zbus::interface(name = "org.freedesktop.PackageKit")]
+impl MockPackageKit {
+    async fn get_transaction_list(&self) -> zbus::fdo::Result<Vec<zvariant::OwnedObjectPath>> {
+        self.transactions
+            .iter()
+            .map(|id| zvariant::OwnedObjectPath::try_from(id.as_str()))
+            .collect::<Result<_, _>>()
+            .map_err(|e| zbus::fdo::Error::Failed(e.to_string()))
+    }
+}
+
+/// A PackageKit transaction served by the mock, which never gets anywhere.
+struct MockTransaction {
+    role: u32,
+    flags: u64,
+}
+
+#[zbus::interface(name = "org.freedesktop.PackageKit.Transaction")]
+impl MockTransaction {
+    #[zbus(property)]
+    async fn role(&self) -> u32 {
+        self.role
+    }
+
+    #[zbus(property)]
+    async fn transaction_flags(&self) -> u64 {
+        self.flags
+    }
+}
+
+/// Enough of logind's manager for the applet to reboot and to block others
+/// from doing so. Rebooting and the programs blocking it are left to the
+/// [`MemoryBackend`].
+struct MockLogind {
+    backend: Arc<MemoryBackend>,
+}
+
+impl MockLogind {
+    fn new(backend: Arc<MemoryBackend>) -> Self {
+        MockLogind { backend }
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.login1.Manager")]
+impl MockLogind {
+    async fn can_reboot(&self) -> Result<String, MockError> {
+        let yes = self.backend.can_reboot().await?;
+        Ok(if yes { "yes" } else { "no" }.to_string())
+    }
+
+    async fn list_inhibitors(
+        &self,
+    ) -> Result<Vec<(String, String, String, String, u32, u32)>, MockError> {
+        let inhibitors = self.backend.shutdown_inhibitors().await?;
+        Ok(inhibitors
+            .into_iter()
+            .map(
+                |Inhibitor {
+                     what,
+                     who,
+                     why,
+                     mode,
+                     uid,
+                     pid,
+                 }| (what, who, why, mode, uid, pid),
+            )
+            .collect())
+    }
+
+    async fn inhibit(
+        &self,
+        _what: &str,
+        who: &str,
+        why: &str,
+        _mode: &str,
+    ) -> Result<zvariant::OwnedFd, MockError> {
+        let lock = self.backend.inhibit_shutdown(who, why).await?;
+        let (ours, theirs) = std::os::unix::net::UnixStream::pair().map_err(zbus::Error::from)?;
+        ours.set_nonblocking(true).map_err(zbus::Error::from)?;
+        let ours = tokio::net::UnixStream::from_std(ours).map_err(zbus::Error::from)?;
+        tokio::spawn(async move {
+            // The client releases the lock by closing its end, which wakes
+            // us with end-of-file.
+            let _ = ours.readable().await;
+            drop(lock);
+        });
+        Ok(std::os::fd::OwnedFd::from(theirs).into())
+    }
+
+    async fn reboot(&self, _interactive: bool) -> Result<(), MockError> {
+        Ok(self.backend.reboot().await?)
+    }
+}
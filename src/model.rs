@@ -0,0 +1,677 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Boot environments and their snapshots, as described by the service.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+
+use zbus::zvariant;
+
+use crate::fl;
+
+/// Represents a boot environment object exposed on the bus.
+#[derive(Debug, Clone)]
+pub struct BootEnvironmentObject {
+    /// The D-Bus object path for this boot environment.
+    pub path: zvariant::OwnedObjectPath,
+    /// The name of this boot environment.
+    pub name: String,
+    /// A description for this boot environment, if any.
+    pub description: Option<String>,
+    /// Whether the system is currently booted into this boot environment.
+    pub active: bool,
+    /// Whether the system will reboot into this environment.
+    pub next_boot: bool,
+    /// Whether the system will reboot into this environment temporarily.
+    pub boot_once: bool,
+    /// Unix timestamp for when this boot environment was created.
+    pub created: i64,
+    /// Where this boot environment is mounted, if it is.
+    pub mountpoint: Option<String>,
+    /// Space used by this boot environment, in bytes.
+    pub space: u64,
+    /// Properties that the applet doesn't recognise, perhaps from a newer
+    /// service, formatted for display.
+    pub other_properties: BTreeMap<String, String>,
+    /// Known properties that were missing or couldn't be decoded, and so
+    /// hold defaults rather than what the service reported.
+    pub undecoded: BTreeSet<&'static str>,
+}
+
+/// Represents a snapshot of a boot environment, as returned by `GetSnapshots`.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    /// The name of this snapshot, in `environment@snapshot` form.
+    pub name: String,
+    /// The full ZFS dataset path of this snapshot.
+    pub path: String,
+    /// A description for this snapshot, if any.
+    pub description: Option<String>,
+    /// Space used by this snapshot, in bytes.
+    pub space: u64,
+    /// Unix timestamp for when this snapshot was created.
+    pub created: i64,
+}
+
+impl From<(String, String, String, u64, i64)> for Snapshot {
+    /// Decode the raw `(name, path, description, space, created)` tuple used
+    /// on the bus.
+    fn from((name, path, description, space, created): (String, String, String, u64, i64)) -> Self {
+        Snapshot {
+            name,
+            path,
+            description: if description.is_empty() {
+                None
+            } else {
+                Some(description)
+            },
+            space,
+            created,
+        }
+    }
+}
+
+/// Build the label used for a boot environment in lists and dropdowns.
+pub(crate) fn environment_label(name: &str, description: Option<&str>) -> String {
+    if let Some(desc) = description {
+        format!("{desc} ({name})")
+    } else {
+        name.to_string()
+    }
+}
+
+impl BootEnvironmentObject {
+    /// The label used for this boot environment in lists and dropdowns.
+    pub fn label(&self) -> String {
+        environment_label(&self.name, self.description.as_deref())
+    }
+
+    /// Localized labels describing the boot status of this environment, e.g.
+    /// whether it is running or the default.
+    pub fn status_labels(&self) -> Vec<String> {
+        let mut labels = Vec::new();
+        if self.active {
+            labels.push(fl!("status-running"));
+        }
+        if self.next_boot {
+            labels.push(fl!("status-default"));
+        }
+        if self.boot_once {
+            labels.push(fl!("status-boot-once"));
+        }
+        labels
+    }
+
+    /// Whether `Active`, `NextBoot` and `BootOnce` all hold what the service
+    /// reported, rather than defaults.
+    pub fn boot_state_known(&self) -> bool {
+        !["Active", "NextBoot", "BootOnce"]
+            .iter()
+            .any(|name| self.undecoded.contains(name))
+    }
+
+    /// Whether `Name` holds what the service reported, rather than a stand-in
+    /// taken from the object path. Renaming, destroying and mounting all rely
+    /// on the real name.
+    pub fn name_known(&self) -> bool {
+        !self.undecoded.contains("Name")
+    }
+
+    /// Whether this boot environment might be the one running. Errs on the
+    /// side of yes when its boot state is unknown.
+    pub fn may_be_active(&self) -> bool {
+        self.active || !self.boot_state_known()
+    }
+
+    /// Whether this boot environment is running without being the default,
+    /// as happens after booting into it once to try it out.
+    pub fn is_temporarily_booted(&self) -> bool {
+        self.boot_state_known() && self.active && !self.next_boot
+    }
+
+    /// Whether this boot environment is safe to destroy, i.e. it is known to
+    /// be neither running nor scheduled to boot, and its name is known for
+    /// the user to confirm.
+    pub fn can_destroy(&self) -> bool {
+        self.name_known()
+            && self.boot_state_known()
+            && !(self.active || self.next_boot || self.boot_once)
+    }
+
+    /// Construct a `BootEnvironmentObject` from a D-Bus dictionary of properties.
+    ///
+    /// Each property that is missing or can't be decoded falls back to a
+    /// default on its own, so that a service which drops or changes one
+    /// doesn't hide the boot environment entirely. What fell back is returned
+    /// alongside it.
+    pub fn from_properties<'a, K, V>(
+        path: zvariant::OwnedObjectPath,
+        props: &'a HashMap<K, V>,
+    ) -> (Self, Vec<PropertyIssue>)
+    where
+        K: std::borrow::Borrow<str> + Eq + std::hash::Hash,
+        V: std::borrow::Borrow<zvariant::Value<'a>>,
+    {
+        let mut issues = Vec::new();
+        let env = BootEnvironmentObject {
+            // The last element of the object path (the GUID, in hex) at least
+            // tells boot environments apart, but it isn't a name that can be
+            // acted on: see `name_known`.
+            name: get_prop(props, "Name", &mut issues).unwrap_or_else(|| {
+                path.as_str()
+                    .rsplit('/')
+                    .next()
+                    .unwrap_or_default()
+                    .to_string()
+            }),
+            description: get_prop(props, "Description", &mut issues).and_then(parse_description),
+            active: get_prop(props, "Active", &mut issues).unwrap_or_default(),
+            next_boot: get_prop(props, "NextBoot", &mut issues).unwrap_or_default(),
+            boot_once: get_prop(props, "BootOnce", &mut issues).unwrap_or_default(),
+            created: get_prop(props, "Created", &mut issues).unwrap_or_default(),
+            mountpoint: get_prop(props, "Mountpoint", &mut issues).and_then(parse_mountpoint),
+            space: get_prop(props, "Space", &mut issues).unwrap_or_default(),
+            other_properties: other_properties(props),
+            undecoded: issues.iter().map(PropertyIssue::name).collect(),
+            path,
+        };
+        (env, issues)
+    }
+
+    /// Apply a partial update from a `PropertiesChanged` signal.
+    pub fn apply(&mut self, changes: BootEnvironmentChanges) {
+        if let Some(name) = changes.name {
+            self.name = name;
+            self.undecoded.remove("Name");
+        }
+        if let Some(description) = changes.description {
+            self.description = description;
+            self.undecoded.remove("Description");
+        }
+        if let Some(active) = changes.active {
+            self.active = active;
+            self.undecoded.remove("Active");
+        }
+        if let Some(next_boot) = changes.next_boot {
+            self.next_boot = next_boot;
+            self.undecoded.remove("NextBoot");
+        }
+        if let Some(boot_once) = changes.boot_once {
+            self.boot_once = boot_once;
+            self.undecoded.remove("BootOnce");
+        }
+        if let Some(created) = changes.created {
+            self.created = created;
+            self.undecoded.remove("Created");
+        }
+        if let Some(mountpoint) = changes.mountpoint {
+            self.mountpoint = mountpoint;
+            self.undecoded.remove("Mountpoint");
+        }
+        if let Some(space) = changes.space {
+            self.space = space;
+            self.undecoded.remove("Space");
+        }
+        self.other_properties.extend(changes.other_properties);
+    }
+}
+
+/// A partial update to a boot environment, decoded from the changed properties
+/// of a `PropertiesChanged` signal. Properties that did not change are `None`.
+#[derive(Debug, Clone, Default)]
+pub struct BootEnvironmentChanges {
+    /// The new name.
+    pub name: Option<String>,
+    /// The new description, which may have been cleared.
+    pub description: Option<Option<String>>,
+    /// Whether the system is now booted into this boot environment.
+    pub active: Option<bool>,
+    /// Whether the system will now reboot into this environment.
+    pub next_boot: Option<bool>,
+    /// Whether the system will now reboot into this environment temporarily.
+    pub boot_once: Option<bool>,
+    /// The new creation time.
+    pub created: Option<i64>,
+    /// The new mountpoint, which may have been unmounted.
+    pub mountpoint: Option<Option<String>>,
+    /// The new space used, in bytes.
+    pub space: Option<u64>,
+    /// Changed properties that the applet doesn't recognise, formatted for
+    /// display.
+    pub other_properties: BTreeMap<String, String>,
+}
+
+impl BootEnvironmentChanges {
+    /// Construct a `BootEnvironmentChanges` from a D-Bus dictionary of changed
+    /// properties.
+    ///
+    /// Properties that can't be decoded are left out, and returned alongside.
+    pub fn from_properties<'a, K, V>(props: &'a HashMap<K, V>) -> (Self, Vec<PropertyIssue>)
+    where
+        K: std::borrow::Borrow<str> + Eq + std::hash::Hash,
+        V: std::borrow::Borrow<zvariant::Value<'a>>,
+    {
+        let mut issues = Vec::new();
+        let changes = BootEnvironmentChanges {
+            name: get_changed_prop(props, "Name", &mut issues),
+            description: get_changed_prop(props, "Description", &mut issues).map(parse_description),
+            active: get_changed_prop(props, "Active", &mut issues),
+            next_boot: get_changed_prop(props, "NextBoot", &mut issues),
+            boot_once: get_changed_prop(props, "BootOnce", &mut issues),
+            created: get_changed_prop(props, "Created", &mut issues),
+            mountpoint: get_changed_prop(props, "Mountpoint", &mut issues).map(parse_mountpoint),
+            space: get_changed_prop(props, "Space", &mut issues),
+            other_properties: other_properties(props),
+        };
+        (changes, issues)
+    }
+}
+
+/// The boot environment properties that the applet understands.
+const KNOWN_PROPERTIES: [&str; 8] = [
+    "Name",
+    "Description",
+    "Active",
+    "NextBoot",
+    "BootOnce",
+    "Created",
+    "Mountpoint",
+    "Space",
+];
+
+/// A property that couldn't be decoded, so that a fallback was used instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyIssue {
+    /// The property was missing.
+    Missing(&'static str),
+    /// The property had a type that can't be converted.
+    WrongType {
+        /// The name of the property.
+        name: &'static str,
+        /// The D-Bus signature of the value it had.
+        signature: String,
+    },
+}
+
+impl PropertyIssue {
+    /// The name of the property concerned.
+    pub fn name(&self) -> &'static str {
+        match self {
+            PropertyIssue::Missing(name) | PropertyIssue::WrongType { name, .. } => name,
+        }
+    }
+}
+
+impl fmt::Display for PropertyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyIssue::Missing(name) => write!(f, "{name} is missing"),
+            PropertyIssue::WrongType { name, signature } => {
+                write!(f, "{name} has unexpected type '{signature}'")
+            }
+        }
+    }
+}
+
+/// A type that properties can be decoded as. Integers are accepted in any
+/// width or signedness that fits, in case the service changes its mind.
+trait PropertyType: Sized {
+    fn decode(value: &zvariant::Value<'_>) -> Option<Self>;
+}
+
+impl PropertyType for String {
+    fn decode(value: &zvariant::Value<'_>) -> Option<Self> {
+        match value {
+            zvariant::Value::Str(s) => Some(s.to_string()),
+            _ => None,
+        }
+    }
+}
+
+impl PropertyType for bool {
+    fn decode(value: &zvariant::Value<'_>) -> Option<Self> {
+        match value {
+            zvariant::Value::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+}
+
+impl PropertyType for i64 {
+    fn decode(value: &zvariant::Value<'_>) -> Option<Self> {
+        match *value {
+            zvariant::Value::U8(n) => Some(n.into()),
+            zvariant::Value::I16(n) => Some(n.into()),
+            zvariant::Value::U16(n) => Some(n.into()),
+            zvariant::Value::I32(n) => Some(n.into()),
+            zvariant::Value::U32(n) => Some(n.into()),
+            zvariant::Value::I64(n) => Some(n),
+            zvariant::Value::U64(n) => n.try_into().ok(),
+            _ => None,
+        }
+    }
+}
+
+impl PropertyType for u64 {
+    fn decode(value: &zvariant::Value<'_>) -> Option<Self> {
+        match *value {
+            zvariant::Value::U8(n) => Some(n.into()),
+            zvariant::Value::I16(n) => n.try_into().ok(),
+            zvariant::Value::U16(n) => Some(n.into()),
+            zvariant::Value::I32(n) => n.try_into().ok(),
+            zvariant::Value::U32(n) => Some(n.into()),
+            zvariant::Value::I64(n) => n.try_into().ok(),
+            zvariant::Value::U64(n) => Some(n),
+            _ => None,
+        }
+    }
+}
+
+/// Decode a property, noting why if it can't be.
+fn get_prop<'a, T, K, V>(
+    props: &'a HashMap<K, V>,
+    name: &'static str,
+    issues: &mut Vec<PropertyIssue>,
+) -> Option<T>
+where
+    K: std::borrow::Borrow<str> + Eq + std::hash::Hash,
+    V: std::borrow::Borrow<zvariant::Value<'a>>,
+    T: PropertyType,
+{
+    if !props.contains_key(name) {
+        issues.push(PropertyIssue::Missing(name));
+    }
+    get_changed_prop(props, name, issues)
+}
+
+/// As get_prop(), but for properties that may be absent.
+fn get_changed_prop<'a, T, K, V>(
+    props: &'a HashMap<K, V>,
+    name: &'static str,
+    issues: &mut Vec<PropertyIssue>,
+) -> Option<T>
+where
+    K: std::borrow::Borrow<str> + Eq + std::hash::Hash,
+    V: std::borrow::Borrow<zvariant::Value<'a>>,
+    T: PropertyType,
+{
+    let mut value = props.get(name)?.borrow();
+    // Look through any variants wrapped in variants.
+    while let zvariant::Value::Value(inner) = value {
+        value = inner;
+    }
+    let decoded = T::decode(value);
+    if decoded.is_none() {
+        issues.push(PropertyIssue::WrongType {
+            name,
+            signature: value.value_signature().to_string(),
+        });
+    }
+    decoded
+}
+
+/// Format the properties that the applet doesn't understand for display.
+fn other_properties<'a, K, V>(props: &'a HashMap<K, V>) -> BTreeMap<String, String>
+where
+    K: std::borrow::Borrow<str> + Eq + std::hash::Hash,
+    V: std::borrow::Borrow<zvariant::Value<'a>>,
+{
+    props
+        .iter()
+        .map(|(name, value)| (name.borrow(), value.borrow()))
+        .filter(|(name, _)| !KNOWN_PROPERTIES.contains(name))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
+
+/// An empty description means there isn't one.
+fn parse_description(description: String) -> Option<String> {
+    if description.is_empty() {
+        None
+    } else {
+        Some(description)
+    }
+}
+
+/// An empty (or `-`) mountpoint means the boot environment isn't mounted.
+fn parse_mountpoint(mountpoint: String) -> Option<String> {
+    if mountpoint.is_empty() || mountpoint == "-" {
+        None
+    } else {
+        Some(mountpoint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use quickcheck::{quickcheck, Arbitrary, Gen, TestResult};
+
+    use super::*;
+
+    /// A property value of any basic type, possibly wrapped in variants.
+    #[derive(Debug, Clone)]
+    enum AnyValue {
+        Bool(bool),
+        U8(u8),
+        I16(i16),
+        U16(u16),
+        I32(i32),
+        U32(u32),
+        I64(i64),
+        U64(u64),
+        F64(f64),
+        Str(String),
+        Strs(Vec<String>),
+        Variant(Box<AnyValue>),
+    }
+
+    impl Arbitrary for AnyValue {
+        fn arbitrary(g: &mut Gen) -> Self {
+            match u8::arbitrary(g) % 12 {
+                0 => AnyValue::Bool(bool::arbitrary(g)),
+                1 => AnyValue::U8(u8::arbitrary(g)),
+                2 => AnyValue::I16(i16::arbitrary(g)),
+                3 => AnyValue::U16(u16::arbitrary(g)),
+                4 => AnyValue::I32(i32::arbitrary(g)),
+                5 => AnyValue::U32(u32::arbitrary(g)),
+                6 => AnyValue::I64(i64::arbitrary(g)),
+                7 => AnyValue::U64(u64::arbitrary(g)),
+                8 => AnyValue::F64(f64::arbitrary(g)),
+                9 => AnyValue::Str(String::arbitrary(g)),
+                10 => AnyValue::Strs(Vec::arbitrary(g)),
+                _ => AnyValue::Variant(Box::new(AnyValue::arbitrary(g))),
+            }
+        }
+    }
+
+    impl AnyValue {
+        fn to_value(&self) -> zvariant::Value<'static> {
+            match self {
+                AnyValue::Bool(b) => zvariant::Value::Bool(*b),
+                AnyValue::U8(n) => zvariant::Value::U8(*n),
+                AnyValue::I16(n) => zvariant::Value::I16(*n),
+                AnyValue::U16(n) => zvariant::Value::U16(*n),
+                AnyValue::I32(n) => zvariant::Value::I32(*n),
+                AnyValue::U32(n) => zvariant::Value::U32(*n),
+                AnyValue::I64(n) => zvariant::Value::I64(*n),
+                AnyValue::U64(n) => zvariant::Value::U64(*n),
+                AnyValue::F64(n) => zvariant::Value::F64(*n),
+                AnyValue::Str(s) => zvariant::Value::from(s.clone()),
+                AnyValue::Strs(strs) => zvariant::Value::from(strs.clone()),
+                AnyValue::Variant(inner) => zvariant::Value::Value(Box::new(inner.to_value())),
+            }
+        }
+    }
+
+    /// A dictionary of properties, named mostly after the ones we know.
+    #[derive(Debug, Clone)]
+    struct Properties(Vec<(String, AnyValue)>);
+
+    impl Arbitrary for Properties {
+        fn arbitrary(g: &mut Gen) -> Self {
+            let len = usize::arbitrary(g) % (KNOWN_PROPERTIES.len() + 4);
+            let props = (0..len)
+                .map(|_| {
+                    let name = if bool::arbitrary(g) {
+                        g.choose(&KNOWN_PROPERTIES).unwrap().to_string()
+                    } else {
+                        String::arbitrary(g)
+                    };
+                    (name, AnyValue::arbitrary(g))
+                })
+                .collect();
+            Properties(props)
+        }
+
+        fn shrink(&self) -> Box<dyn Iterator<Item = Self>> {
+            Box::new(self.0.shrink().map(Properties))
+        }
+    }
+
+    impl Properties {
+        fn to_values(&self) -> HashMap<String, zvariant::Value<'static>> {
+            self.0
+                .iter()
+                .map(|(name, value)| (name.clone(), value.to_value()))
+                .collect()
+        }
+    }
+
+    fn path() -> zvariant::OwnedObjectPath {
+        zvariant::OwnedObjectPath::try_from("/ca/kamacite/BootEnvironments/default").unwrap()
+    }
+
+    quickcheck! {
+        fn every_object_can_be_parsed(props: Properties) -> bool {
+            let values = props.to_values();
+            let (env, issues) = BootEnvironmentObject::from_properties(path(), &values);
+
+            // Each known property is decoded or reported, once.
+            let reported = KNOWN_PROPERTIES.iter().all(|name| {
+                let count = issues.iter().filter(|issue| issue.name() == *name).count();
+                let missing = issues.contains(&PropertyIssue::Missing(name));
+                count <= 1 && missing != values.contains_key(*name)
+            });
+            // Everything else is kept.
+            let kept = values.keys().all(|name| {
+                KNOWN_PROPERTIES.contains(&name.as_str()) != env.other_properties.contains_key(name)
+            });
+            reported && kept && env.path == path()
+        }
+
+        fn changes_only_cover_changed_properties(props: Properties) -> bool {
+            let values = props.to_values();
+            let (changes, issues) = BootEnvironmentChanges::from_properties(&values);
+
+            let changed = |name: &str, present: bool| !present || values.contains_key(name);
+            changed("Name", changes.name.is_some())
+                && changed("Description", changes.description.is_some())
+                && changed("Active", changes.active.is_some())
+                && changed("NextBoot", changes.next_boot.is_some())
+                && changed("BootOnce", changes.boot_once.is_some())
+                && changed("Created", changes.created.is_some())
+                && changed("Mountpoint", changes.mountpoint.is_some())
+                && changed("Space", changes.space.is_some())
+                && issues.iter().all(|issue| {
+                    matches!(issue, PropertyIssue::WrongType { .. })
+                        && values.contains_key(issue.name())
+                })
+        }
+
+        fn well_typed_objects_round_trip(
+            strings: (String, String, String),
+            flags: (bool, bool, bool),
+            created: i64,
+            space: u64
+        ) -> bool {
+            let (name, description, mountpoint) = strings;
+            let (active, next_boot, boot_once) = flags;
+            let values = HashMap::from([
+                ("Name", zvariant::Value::from(name.clone())),
+                ("Description", zvariant::Value::from(description.clone())),
+                ("Active", zvariant::Value::from(active)),
+                ("NextBoot", zvariant::Value::from(next_boot)),
+                ("BootOnce", zvariant::Value::from(boot_once)),
+                ("Created", zvariant::Value::from(created)),
+                ("Mountpoint", zvariant::Value::from(mountpoint.clone())),
+                ("Space", zvariant::Value::from(space)),
+            ]);
+            let (env, issues) = BootEnvironmentObject::from_properties(path(), &values);
+
+            issues.is_empty()
+                && env.name == name
+                && env.description == parse_description(description)
+                && env.active == active
+                && env.next_boot == next_boot
+                && env.boot_once == boot_once
+                && env.created == created
+                && env.mountpoint == parse_mountpoint(mountpoint)
+                && env.space == space
+                && env.other_properties.is_empty()
+        }
+
+        fn unknown_boot_state_prevents_destruction(props: Properties) -> TestResult {
+            let values = props.to_values();
+            let (env, issues) = BootEnvironmentObject::from_properties(path(), &values);
+
+            let flag_fell_back = issues
+                .iter()
+                .any(|issue| ["Active", "NextBoot", "BootOnce"].contains(&issue.name()));
+            if !flag_fell_back {
+                return TestResult::discard();
+            }
+            TestResult::from_bool(!env.can_destroy() && !env.is_temporarily_booted())
+        }
+
+        fn sizes_are_accepted_in_any_integer_type(size: i64) -> TestResult {
+            let values = HashMap::from([("Space", zvariant::Value::from(size))]);
+            let (changes, issues) = BootEnvironmentChanges::from_properties(&values);
+            match u64::try_from(size) {
+                Ok(size) => TestResult::from_bool(changes.space == Some(size) && issues.is_empty()),
+                Err(_) => TestResult::from_bool(changes.space.is_none() && issues.len() == 1),
+            }
+        }
+    }
+
+    #[test]
+    fn missing_properties_fall_back() {
+        let values = HashMap::from([
+            ("Active", zvariant::Value::from("yes")),
+            ("Space", zvariant::Value::from(4096u32)),
+            ("Encrypted", zvariant::Value::from(true)),
+        ]);
+        let (env, issues) = BootEnvironmentObject::from_properties(path(), &values);
+
+        assert_eq!(env.name, "default");
+        assert!(!env.name_known());
+        assert!(!env.active);
+        assert!(!env.can_destroy());
+        assert_eq!(env.space, 4096);
+        assert_eq!(
+            env.other_properties,
+            BTreeMap::from([("Encrypted".to_string(), "true".to_string())])
+        );
+        assert!(issues.contains(&PropertyIssue::WrongType {
+            name: "Active",
+            signature: "s".to_string(),
+        }));
+        assert!(issues.contains(&PropertyIssue::Missing("Name")));
+        assert_eq!(issues.len(), 7);
+    }
+
+    #[test]
+    fn nested_variants_are_unwrapped() {
+        let values = HashMap::from([(
+            "NextBoot",
+            zvariant::Value::Value(Box::new(zvariant::Value::from(true))),
+        )]);
+        let (changes, issues) = BootEnvironmentChanges::from_properties(&values);
+
+        assert_eq!(changes.next_boot, Some(true));
+        assert!(issues.is_empty());
+    }
+}
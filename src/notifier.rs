@@ -0,0 +1,272 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Desktop notifications, sent through the notification server on the
+//! session bus or kept in memory for demos and tests.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+
+use futures_util::future::{self, BoxFuture};
+use futures_util::stream::{self, BoxStream};
+use futures_util::{FutureExt, StreamExt};
+use tokio::sync::broadcast;
+
+use crate::error::AppError;
+use crate::notifications::NotificationsProxy;
+
+/// The name notifications are sent under.
+const APP_NAME: &str = "Boot Environments";
+
+/// The icon shown on notifications.
+const APP_ICON: &str = "drive-multidisk-symbolic";
+
+/// A desktop notification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// A one-line overview.
+    pub summary: String,
+    /// The details, if any.
+    pub body: String,
+    /// Buttons on the notification, as pairs of an action key and a label.
+    pub actions: Vec<(String, String)>,
+    /// An earlier notification to replace, if it is still shown.
+    pub replaces: Option<u32>,
+}
+
+/// Something that can show desktop notifications and report the buttons
+/// clicked on them.
+pub trait Notifier: std::fmt::Debug + Send + Sync {
+    /// Show a notification, returning its ID.
+    fn notify(&self, notification: Notification) -> BoxFuture<'_, Result<u32, AppError>>;
+
+    /// A stream of the buttons clicked on notifications, as pairs of a
+    /// notification ID and an action key.
+    fn actions(&self) -> BoxStream<'static, (u32, String)>;
+}
+
+/// Sends notifications to the notification server on the session bus.
+#[derive(Debug, Clone)]
+pub struct DBusNotifier {
+    conn: zbus::Connection,
+}
+
+impl DBusNotifier {
+    /// Connect to the notification server on the session bus.
+    pub async fn session() -> Result<Self, AppError> {
+        Ok(DBusNotifier::new(zbus::Connection::session().await?))
+    }
+
+    /// Use the notification server on an existing connection.
+    pub fn new(conn: zbus::Connection) -> Self {
+        DBusNotifier { conn }
+    }
+
+    async fn proxy(&self) -> Result<NotificationsProxy<'static>, zbus::Error> {
+        NotificationsProxy::new(&self.conn).await
+    }
+
+    async fn action_events(&self) -> Result<BoxStream<'static, (u32, String)>, zbus::Error> {
+        let stream = self.proxy().await?.receive_action_invoked().await?;
+        Ok(stream
+            .filter_map(|signal| async move {
+                let args = signal.args().ok()?;
+                Some((*args.id(), args.action_key().to_string()))
+            })
+            .boxed())
+    }
+}
+
+impl Notifier for DBusNotifier {
+    fn notify(&self, notification: Notification) -> BoxFuture<'_, Result<u32, AppError>> {
+        Box::pin(async move {
+            // Actions are sent as a flat list of keys and labels.
+            let actions: Vec<&str> = notification
+                .actions
+                .iter()
+                .flat_map(|(key, label)| [key.as_str(), label.as_str()])
+                .collect();
+            Ok(self
+                .proxy()
+                .await?
+                .notify(
+                    APP_NAME,
+                    notification.replaces.unwrap_or(0),
+                    APP_ICON,
+                    &notification.summary,
+                    &notification.body,
+                    &actions,
+                    HashMap::new(),
+                    -1,
+                )
+                .await?)
+        })
+    }
+
+    fn actions(&self) -> BoxStream<'static, (u32, String)> {
+        let this = self.clone();
+        async move { this.action_events().await }
+            .map(|result| {
+                result.unwrap_or_else(|e| {
+                    tracing::error!(error = ?e, "Failed to listen for notification actions");
+                    stream::empty().boxed()
+                })
+            })
+            .flatten_stream()
+            .boxed()
+    }
+}
+
+/// Keeps notifications in memory, for demos and tests.
+#[derive(Debug)]
+pub struct MemoryNotifier {
+    sent: Mutex<Vec<(u32, Notification)>>,
+    actions: broadcast::Sender<(u32, String)>,
+}
+
+impl Default for MemoryNotifier {
+    fn default() -> Self {
+        let (actions, _) = broadcast::channel(8);
+        MemoryNotifier {
+            sent: Mutex::default(),
+            actions,
+        }
+    }
+}
+
+impl MemoryNotifier {
+    /// The notifications shown so far, oldest first, with their IDs.
+    pub fn sent(&self) -> Vec<(u32, Notification)> {
+        self.lock().clone()
+    }
+
+    /// Pretend that the user clicked a button on a notification.
+    pub fn invoke(&self, id: u32, action: &str) {
+        let _ = self.actions.send((id, action.to_string()));
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<(u32, Notification)>> {
+        self.sent.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Notifier for MemoryNotifier {
+    fn notify(&self, notification: Notification) -> BoxFuture<'_, Result<u32, AppError>> {
+        tracing::info!(?notification, "Pretending to show a notification");
+        let mut sent = self.lock();
+        // IDs start at 1, as 0 means "replace nothing".
+        let id = notification
+            .replaces
+            .unwrap_or_else(|| u32::try_from(sent.len() + 1).unwrap_or(u32::MAX));
+        sent.push((id, notification));
+        Box::pin(future::ready(Ok(id)))
+    }
+
+    fn actions(&self) -> BoxStream<'static, (u32, String)> {
+        stream::unfold(self.actions.subscribe(), |mut rx| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(action) => return Some((action, rx)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use zbus::object_server::SignalEmitter;
+    use zbus::zvariant;
+
+    use super::*;
+    use crate::mock_service::TestBus;
+
+    /// How long to wait for a signal before giving up.
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Where notification servers live.
+    const PATH: &str = "/org/freedesktop/Notifications";
+
+    /// A notification server that remembers the buttons it was asked to
+    /// show.
+    #[derive(Default)]
+    struct MockServer {
+        shown: Arc<Mutex<Vec<Vec<String>>>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.Notifications")]
+    impl MockServer {
+        #[allow(clippy::too_many_arguments)]
+        fn notify(
+            &self,
+            _app_name: &str,
+            _replaces_id: u32,
+            _app_icon: &str,
+            _summary: &str,
+            _body: &str,
+            actions: Vec<String>,
+            _hints: HashMap<String, zvariant::OwnedValue>,
+            _expire_timeout: i32,
+        ) -> u32 {
+            let mut shown = self.shown.lock().unwrap();
+            shown.push(actions);
+            u32::try_from(shown.len()).unwrap_or(u32::MAX)
+        }
+
+        #[zbus(signal)]
+        async fn action_invoked(
+            emitter: &SignalEmitter<'_>,
+            id: u32,
+            action_key: &str,
+        ) -> zbus::Result<()>;
+    }
+
+    #[tokio::test]
+    async fn notifications_and_actions_go_over_the_bus() {
+        let bus = TestBus::start().expect("dbus-daemon is needed to run D-Bus tests");
+        let server = MockServer::default();
+        let shown = server.shown.clone();
+        let server_conn = bus.connect().await.unwrap();
+        server_conn.object_server().at(PATH, server).await.unwrap();
+        server_conn
+            .request_name("org.freedesktop.Notifications")
+            .await
+            .unwrap();
+        let notifier = DBusNotifier::new(bus.connect().await.unwrap());
+        let mut actions = notifier.action_events().await.unwrap();
+
+        let id = notifier
+            .notify(Notification {
+                summary: "Default changed".to_string(),
+                body: String::new(),
+                actions: vec![("undo".to_string(), "Undo".to_string())],
+                replaces: None,
+            })
+            .await
+            .unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(shown.lock().unwrap()[0], ["undo", "Undo"]);
+
+        let iface = server_conn
+            .object_server()
+            .interface::<_, MockServer>(PATH)
+            .await
+            .unwrap();
+        MockServer::action_invoked(iface.signal_emitter(), id, "undo")
+            .await
+            .unwrap();
+        let action = tokio::time::timeout(TIMEOUT, actions.next())
+            .await
+            .expect("timed out waiting for a signal");
+        assert_eq!(action, Some((1, "undo".to_string())));
+    }
+}
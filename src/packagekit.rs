@@ -0,0 +1,98 @@
+//! # D-Bus interface proxies for: `org.freedesktop.PackageKit`, `org.freedesktop.PackageKit.Transaction`
+//!
+//! Adapted from code generated by `zbus-xmlgen` `5.1.0` from D-Bus introspection data.
+//! Source: `Interface '/org/freedesktop/PackageKit' from service 'org.freedesktop.PackageKit' on system bus`.
+//!
+//! Only the parts of the interfaces that the applet uses are included, along
+//! with [`PackageTransaction`] for making sense of them.
+//!
+//! More information can be found in the [Writing a client proxy] section of the zbus
+//! documentation.
+//!
+//! [Writing a client proxy]: https://dbus2.github.io/zbus/client.html
+use zbus::proxy;
+
+use crate::fl;
+
+#[proxy(
+    interface = "org.freedesktop.PackageKit",
+    default_service = "org.freedesktop.PackageKit",
+    default_path = "/org/freedesktop/PackageKit"
+)]
+pub trait PackageKit {
+    /// GetTransactionList method
+    fn get_transaction_list(&self) -> zbus::Result<Vec<zbus::zvariant::OwnedObjectPath>>;
+}
+
+#[proxy(
+    interface = "org.freedesktop.PackageKit.Transaction",
+    default_service = "org.freedesktop.PackageKit"
+)]
+pub trait Transaction {
+    /// Role property
+    #[zbus(property)]
+    fn role(&self) -> zbus::Result<u32>;
+
+    /// TransactionFlags property
+    #[zbus(property)]
+    fn transaction_flags(&self) -> zbus::Result<u64>;
+}
+
+/// PackageKit's role for a transaction that updates packages.
+const ROLE_UPDATE_PACKAGES: u32 = 22;
+
+/// PackageKit's role for a transaction that upgrades to a new distribution
+/// release.
+const ROLE_UPGRADE_SYSTEM: u32 = 33;
+
+/// The PackageKit transaction flag for dry runs.
+const FLAG_SIMULATE: u64 = 1 << 2;
+
+/// The PackageKit transaction flag for fetching packages without installing
+/// them.
+const FLAG_ONLY_DOWNLOAD: u64 = 1 << 3;
+
+/// What a PackageKit transaction is about to do to the installed packages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpgradeKind {
+    /// Update some or all packages.
+    UpdatePackages,
+    /// Upgrade to a new distribution release.
+    UpgradeSystem,
+}
+
+/// A PackageKit transaction that is about to update the system.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageTransaction {
+    /// The transaction's object path, which PackageKit also uses as its ID.
+    pub id: String,
+    /// What the transaction does.
+    pub kind: UpgradeKind,
+}
+
+impl PackageTransaction {
+    /// Interpret a transaction's `Role` and `TransactionFlags` properties,
+    /// or `None` if it won't update anything: other roles, dry runs and
+    /// downloads are all left alone.
+    pub fn new(id: String, role: u32, flags: u64) -> Option<Self> {
+        let kind = match role {
+            ROLE_UPDATE_PACKAGES => UpgradeKind::UpdatePackages,
+            ROLE_UPGRADE_SYSTEM => UpgradeKind::UpgradeSystem,
+            _ => return None,
+        };
+        if flags & (FLAG_SIMULATE | FLAG_ONLY_DOWNLOAD) != 0 {
+            return None;
+        }
+        Some(PackageTransaction { id, kind })
+    }
+
+    /// A description for a snapshot taken before this transaction, naming
+    /// it so that the snapshot can be matched up with the package history.
+    pub fn snapshot_description(&self) -> String {
+        let what = match self.kind {
+            UpgradeKind::UpdatePackages => fl!("snapshot-before-update"),
+            UpgradeKind::UpgradeSystem => fl!("snapshot-before-system-upgrade"),
+        };
+        format!("{what} ({})", self.id)
+    }
+}
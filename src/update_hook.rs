@@ -0,0 +1,226 @@
+// SPDX-License-Identifier: MPL-2.0
+
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Snapshots the running boot environment before the package manager changes
+//! it.
+//!
+//! PackageKit starts a transaction without waiting for anyone watching it, so
+//! the snapshot is taken from the package manager's own hooks instead:
+//! APT's `DPkg::Pre-Install-Pkgs` and pacman's `PreTransaction` hooks both
+//! run before anything is unpacked, and hold the transaction until they
+//! finish. The hooks in `resources/` run the applet's binary with
+//! [`ARG`], passing the packages on standard input, whether the update came
+//! from PackageKit or the command line.
+
+use std::io::BufRead;
+
+use cosmic::cosmic_config::{self, CosmicConfigEntry};
+use cosmic::Application;
+
+use crate::app::AppModel;
+use crate::backend::{Backend, DBusBackend};
+use crate::config::Config;
+use crate::error::AppError;
+use crate::fl;
+use crate::model::Snapshot;
+
+/// The command-line argument that runs the hook instead of the applet.
+pub const ARG: &str = "--snapshot-before-update";
+
+/// The most packages named in a snapshot's description.
+const MAX_NAMED_PACKAGES: usize = 3;
+
+/// Take a snapshot for the package manager, reading the packages it is about
+/// to change from standard input.
+///
+/// Failures are logged rather than reported to the package manager: a
+/// missing snapshot isn't worth holding back updates over.
+pub fn run() {
+    let config = match cosmic_config::Config::system(AppModel::APP_ID, Config::VERSION) {
+        Ok(handler) => Config::get_entry(&handler).unwrap_or_else(|(errors, config)| {
+            for error in errors {
+                tracing::error!(?error, "Failed to load a setting");
+            }
+            config
+        }),
+        Err(e) => {
+            tracing::error!(error = ?e, "Failed to open system settings");
+            Config::default()
+        }
+    };
+    if !config.snapshot_before_upgrades {
+        tracing::info!("Snapshots before updates are turned off");
+        return;
+    }
+
+    let packages: Vec<String> = std::io::stdin()
+        .lock()
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| package_name(&line).map(str::to_string))
+        .collect();
+
+    let result = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| AppError::Other(e.to_string()))
+        .and_then(|runtime| {
+            runtime.block_on(async {
+                let backend = DBusBackend::system().await?;
+                snapshot_before_update(&backend, &packages).await
+            })
+        });
+    match result {
+        Ok(Some(name)) => tracing::info!(name, "Created snapshot before an update"),
+        Ok(None) => tracing::warn!("No running boot environment to snapshot"),
+        Err(e) => tracing::error!(error = ?e, "Failed to create snapshot before an update"),
+    }
+}
+
+/// Snapshot the running boot environment before `packages` are changed,
+/// returning the new snapshot's name, or `None` if no boot environment is
+/// running.
+pub async fn snapshot_before_update(
+    backend: &dyn Backend,
+    packages: &[String],
+) -> Result<Option<String>, AppError> {
+    let Some(env) = backend.list().await?.into_iter().find(|env| env.active) else {
+        return Ok(None);
+    };
+    let description = match backend.update_transaction().await {
+        Some(transaction) => transaction.snapshot_description(),
+        None => describe_packages(packages),
+    };
+    let existing = backend.snapshots(&env.path).await?;
+    let name = unique_snapshot_name(&existing, &env.name, chrono::Local::now());
+    backend
+        .snapshot(&env.path, &name, &description)
+        .await
+        .map(Some)
+}
+
+/// The name of the package in a line passed by a package manager hook: a
+/// path to a `.deb` file from APT, or a package name from pacman.
+fn package_name(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let name = match line.strip_suffix(".deb") {
+        // APT names files `<package>_<version>_<arch>.deb`.
+        Some(path) => path.rsplit('/').next()?.split('_').next()?,
+        None => line,
+    };
+    (!name.is_empty()).then_some(name)
+}
+
+/// Describe a snapshot taken before the package manager changes `packages`,
+/// for updates that didn't come through PackageKit.
+fn describe_packages(packages: &[String]) -> String {
+    if packages.is_empty() {
+        return fl!("snapshot-before-update");
+    }
+    let mut named = packages[..packages.len().min(MAX_NAMED_PACKAGES)].join(", ");
+    if packages.len() > MAX_NAMED_PACKAGES {
+        named.push_str(", …");
+    }
+    fl!(
+        "snapshot-before-packages",
+        count = packages.len(),
+        packages = named
+    )
+}
+
+/// A timestamped name for a snapshot of `env` that none of `existing` has
+/// already taken. Timestamps only have a resolution of a second, so updates
+/// in quick succession are told apart by a counter.
+fn unique_snapshot_name(
+    existing: &[Snapshot],
+    env: &str,
+    now: chrono::DateTime<chrono::Local>,
+) -> String {
+    let base = now.format("update-%Y-%m-%d-%H:%M:%S").to_string();
+    let taken = |name: &str| existing.iter().any(|s| s.name == format!("{env}@{name}"));
+    let mut name = base.clone();
+    let mut count = 1;
+    while taken(&name) {
+        count += 1;
+        name = format!("{base}-{count}");
+    }
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::MemoryBackend;
+
+    #[test]
+    fn package_names_are_read_from_either_package_manager() {
+        assert_eq!(
+            package_name("/var/cache/apt/archives/linux-image-6.8_6.8.0-31.31_amd64.deb\n"),
+            Some("linux-image-6.8")
+        );
+        assert_eq!(package_name("linux_lts"), Some("linux_lts"));
+        assert_eq!(package_name("   "), None);
+    }
+
+    #[tokio::test]
+    async fn updates_snapshot_the_running_environment() {
+        let backend = MemoryBackend::demo();
+        let packages = ["linux", "mesa", "systemd", "zfs-utils"].map(str::to_string);
+
+        let first = snapshot_before_update(&backend, &packages).await.unwrap();
+        let second = snapshot_before_update(&backend, &packages[..1])
+            .await
+            .unwrap();
+
+        let env = backend
+            .list()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|env| env.active)
+            .unwrap();
+        let snapshots = backend.snapshots(&env.path).await.unwrap();
+        let first = snapshots
+            .iter()
+            .find(|s| Some(&s.name) == first.as_ref())
+            .unwrap();
+        let second = snapshots
+            .iter()
+            .find(|s| Some(&s.name) == second.as_ref())
+            .unwrap();
+        // Both may well have been taken in the same second.
+        assert_ne!(first.name, second.name);
+        let description = first.description.as_deref().unwrap();
+        assert!(
+            description.contains("linux, mesa, systemd, …"),
+            "{description}"
+        );
+    }
+
+    #[test]
+    fn snapshot_names_in_the_same_second_are_told_apart() {
+        let now = chrono::Local::now();
+        let base = now.format("update-%Y-%m-%d-%H:%M:%S").to_string();
+        let snapshot = |name: String| Snapshot {
+            path: format!("zroot/ROOT/{name}"),
+            name,
+            description: None,
+            space: 0,
+            created: now.timestamp(),
+        };
+
+        let existing = vec![
+            snapshot(format!("default@{base}")),
+            snapshot(format!("default@{base}-2")),
+            snapshot(format!("other@{base}-3")),
+        ];
+        assert_eq!(
+            unique_snapshot_name(&existing, "default", now),
+            format!("{base}-3")
+        );
+        assert_eq!(unique_snapshot_name(&existing, "other", now), base);
+    }
+}